@@ -0,0 +1,100 @@
+use criterion::{black_box, criterion_group, criterion_main, BatchSize, Criterion};
+use nalgebra::Matrix4;
+use threes::{
+    math::series::Series,
+    model::{
+        bitboard::{Bitboard, Tables},
+        direction::Direction,
+        grid::Grid,
+        tile::Tile,
+    },
+    rng,
+    rules::RuleSet,
+    state::State,
+};
+
+// a mid-game position every direction moves in
+fn position() -> Grid {
+    Grid::from(Matrix4::new(
+        1, 0, 3, 6, 2, 3, 12, 0, 0, 24, 6, 3, 48, 3, 0, 1,
+    ))
+}
+
+// a full position no tiles combine in, which every line has to be checked for
+fn stuck() -> Grid {
+    Grid::from(Matrix4::new(3, 6, 3, 6, 6, 3, 6, 3, 3, 6, 3, 6, 6, 3, 6, 3))
+}
+
+fn shift(c: &mut Criterion) {
+    let rules = RuleSet::Threes.rules();
+    let grid = position();
+    let mut r = rng::from_seed(42);
+    c.bench_function("grid shift", |b| {
+        b.iter(|| {
+            for dir in Direction::ALL {
+                black_box(grid.shift(rules, &mut r, dir, 3));
+            }
+        })
+    });
+    let tables = Tables::of(RuleSet::Threes);
+    let board = Bitboard::from_grid(tables, &grid).unwrap();
+    c.bench_function("bitboard shift", |b| {
+        b.iter(|| {
+            for dir in Direction::ALL {
+                black_box(black_box(board).shift(tables, &mut r, dir, 3));
+            }
+        })
+    });
+}
+
+fn game_over(c: &mut Criterion) {
+    let rules = RuleSet::Threes.rules();
+    let grid = stuck();
+    c.bench_function("grid game over", |b| {
+        b.iter(|| black_box(&grid).game_over(rules))
+    });
+    c.bench_function("grid legal moves", |b| {
+        b.iter(|| black_box(&grid).legal_moves(rules))
+    });
+    let tables = Tables::of(RuleSet::Threes);
+    let board = Bitboard::from_grid(tables, &grid).unwrap();
+    c.bench_function("bitboard game over", |b| {
+        b.iter(|| black_box(board).game_over(tables))
+    });
+}
+
+fn tiles(c: &mut Criterion) {
+    let mut r = rng::from_seed(42);
+    let tile = Tile::new(&mut r);
+    c.bench_function("tile next", |b| {
+        b.iter(|| black_box(tile).next(&mut r, 384))
+    });
+    let tile = Tile::from_deck(&mut r);
+    c.bench_function("tile draw", |b| {
+        b.iter(|| black_box(tile).draw(&mut r, 384))
+    });
+    let series = Series::new(1, 2, 2);
+    c.bench_function("series n", |b| {
+        b.iter(|| {
+            for value in [1, 2, 3, 6, 12, 24, 48, 96, 192, 384, 768] {
+                black_box(series.n(black_box(value)));
+            }
+        })
+    });
+}
+
+fn score(c: &mut Criterion) {
+    let state = State::from_position(42, position(), 3, RuleSet::Threes);
+    c.bench_function("state score", |b| b.iter(|| black_box(&state).score()));
+    // the state is moved in, hence the batches
+    c.bench_function("state shift", |b| {
+        b.iter_batched(
+            || state.clone(),
+            |mut s| s.shift(Direction::Left),
+            BatchSize::SmallInput,
+        )
+    });
+}
+
+criterion_group!(benches, shift, game_over, tiles, score);
+criterion_main!(benches);
@@ -0,0 +1,56 @@
+use criterion::{black_box, criterion_group, criterion_main, BatchSize, Criterion};
+use threes::{
+    ai::{expectimax::Expectimax, strategy::Random},
+    model::size::Size,
+    rules::RuleSet,
+    simulate::play,
+    state::State,
+};
+
+// games are played in turn from these so that every run measures the same ones
+const SEEDS: [u64; 8] = [1, 2, 3, 4, 5, 6, 7, 8];
+
+fn random_games(c: &mut Criterion) {
+    let mut group = c.benchmark_group("random game");
+    for rules in RuleSet::ALL {
+        let mut seeds = SEEDS.iter().copied().cycle();
+        group.bench_function(rules.to_string(), |b| {
+            b.iter_batched(
+                || seeds.next().map(|seed| (Random::new(seed), seed)).unwrap(),
+                |(mut strategy, seed)| play(&mut strategy, seed, Size::default(), rules).unwrap(),
+                BatchSize::SmallInput,
+            )
+        });
+    }
+    group.finish();
+}
+
+// how long the ai takes to pick a move, from the start and from later in a game
+fn search(c: &mut Criterion) {
+    let mut group = c.benchmark_group("expectimax");
+    group.sample_size(20);
+    let start = State::new(42);
+    let mut later = State::new(42);
+    let ai = Expectimax::new(2);
+    for _ in 0..50 {
+        match ai.best_move(&later) {
+            Some(dir) => {
+                later.shift(dir);
+            }
+            None => break,
+        }
+    }
+    for depth in [1, 2, 3] {
+        let ai = Expectimax::new(depth);
+        group.bench_function(format!("depth {} start", depth), |b| {
+            b.iter(|| ai.best_move(black_box(&start)))
+        });
+        group.bench_function(format!("depth {} later", depth), |b| {
+            b.iter(|| ai.best_move(black_box(&later)))
+        });
+    }
+    group.finish();
+}
+
+criterion_group!(benches, random_games, search);
+criterion_main!(benches);
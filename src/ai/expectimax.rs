@@ -0,0 +1,192 @@
+use crate::{
+    model::{direction::Direction, grid::Grid, tile::Tile},
+    rules::Rules,
+    state::State,
+};
+
+use super::heuristics::Heuristics;
+
+// value of a grid in which no move is possible
+const GAME_OVER: f64 = -1e9;
+
+// maximizes over the player's moves and averages over where the next tile lands and what the
+// tile after it is
+#[derive(Debug, Clone, Copy)]
+pub struct Expectimax {
+    // number of moves looked ahead, at least 1
+    depth: u32,
+    heuristics: Heuristics,
+}
+
+impl Expectimax {
+    pub fn new(depth: u32) -> Expectimax {
+        Expectimax {
+            depth: depth.max(1),
+            heuristics: Heuristics::default(),
+        }
+    }
+
+    pub fn heuristics(mut self, heuristics: Heuristics) -> Expectimax {
+        self.heuristics = heuristics;
+        self
+    }
+
+    // the expected value of every legal move
+    pub fn evaluate(&self, state: &State) -> Vec<(Direction, f64)> {
+        if state.is_game_over() {
+            return Vec::new();
+        }
+        let rules = state.rules().rules();
+        Direction::ALL
+            .into_iter()
+            .filter_map(|d| {
+                self.chance(rules, state.grid(), *state.tile(), d, self.depth)
+                    .map(|v| (d, v))
+            })
+            .collect()
+    }
+
+    pub fn best_move(&self, state: &State) -> Option<Direction> {
+        self.evaluate(state)
+            .into_iter()
+            .max_by(|(_, a), (_, b)| a.total_cmp(b))
+            .map(|(d, _)| d)
+    }
+
+    fn max(&self, rules: &dyn Rules, grid: &Grid, tile: Tile, depth: u32) -> f64 {
+        Direction::ALL
+            .into_iter()
+            .filter_map(|d| self.chance(rules, grid, tile, d, depth))
+            .max_by(f64::total_cmp)
+            .unwrap_or(GAME_OVER)
+    }
+
+    // none if the move is not legal, a bonus tile is only known by its preview so every candidate
+    // is as likely to spawn
+    fn chance(
+        &self,
+        rules: &dyn Rules,
+        grid: &Grid,
+        tile: Tile,
+        dir: Direction,
+        depth: u32,
+    ) -> Option<f64> {
+        let candidates = tile.preview();
+        let p_candidate = 1.0 / candidates.len() as f64;
+        let mut value = 0.0;
+        for candidate in candidates {
+            let outcomes = grid.shift_outcomes(rules, dir, candidate);
+            if outcomes.is_empty() {
+                return None;
+            }
+            let v: f64 = outcomes
+                .into_iter()
+                .map(|(g, p)| {
+                    if depth <= 1 {
+                        p * self.heuristics.evaluate(rules, &g)
+                    } else {
+                        let next_tiles = previewed(rules.tile_outcomes(tile, g.matrix.max()));
+                        let v: f64 = next_tiles
+                            .into_iter()
+                            .map(|(t, q)| q * self.max(rules, &g, t, depth - 1))
+                            .sum();
+                        p * v
+                    }
+                })
+                .sum();
+            value += p_candidate * v;
+        }
+        Some(value)
+    }
+}
+
+// bonus tiles previewed alike are worth the same, they are evaluated once
+fn previewed(outcomes: Vec<(Tile, f64)>) -> Vec<(Tile, f64)> {
+    let (bonuses, mut tiles): (Vec<_>, Vec<_>) =
+        outcomes.into_iter().partition(|(t, _)| t.is_bonus());
+    if let Some((t, _)) = bonuses.first() {
+        tiles.push((*t, bonuses.iter().map(|(_, p)| p).sum()));
+    }
+    tiles
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{model::size::Size, rules::RuleSet};
+
+    use super::*;
+
+    #[test]
+    fn best_move_is_legal() {
+        let ai = Expectimax::new(2);
+        let mut s = State::new(7);
+        for _ in 0..20 {
+            match ai.best_move(&s) {
+                Some(d) => {
+                    assert!(s.legal_moves().contains(&d));
+                    s.shift(d);
+                }
+                None => break,
+            }
+        }
+    }
+
+    #[test]
+    fn best_move_is_none_if_game_over() {
+        let s =
+            State::from_notation("1,3,1,3/3,1,3,1/1,3,1,3/3,1,3,1 1", 7, RuleSet::Threes).unwrap();
+        assert!(s.legal_moves().is_empty());
+        assert_eq!(Expectimax::new(2).best_move(&s), None);
+    }
+
+    #[test]
+    fn best_move_merges_the_96s() {
+        // down only slides the first column
+        let s = State::from_notation("96,96,1,3/1,3,6,1/3,1,3,6/-,3,1,3 3", 7, RuleSet::Threes)
+            .unwrap();
+        let best = Expectimax::new(1).best_move(&s);
+        assert!(best == Some(Direction::Left) || best == Some(Direction::Right));
+    }
+
+    #[test]
+    fn bonus_tiles_are_evaluated_by_their_preview() {
+        let position = "192,48,-,-/3,6,-,-/1,2,-,-/-,-,-,- 12/24/48";
+        let states: Vec<State> = (0..20)
+            .map(|seed| State::from_notation(position, seed, RuleSet::Threes).unwrap())
+            .collect();
+        let ai = Expectimax::new(2);
+        let first = &states[0];
+        let other = states
+            .iter()
+            .find(|s| s.next_tile() != first.next_tile())
+            .unwrap();
+        assert_eq!(ai.evaluate(first), ai.evaluate(other));
+    }
+
+    #[test]
+    fn evaluate_covers_legal_moves() {
+        let s = State::new(7);
+        let evaluated: Vec<Direction> = Expectimax::new(1)
+            .evaluate(&s)
+            .into_iter()
+            .map(|(d, _)| d)
+            .collect();
+        assert_eq!(evaluated, s.legal_moves());
+    }
+
+    #[test]
+    fn plays_2048() {
+        let ai = Expectimax::new(1);
+        let mut s = State::with_rules(7, Size::default(), RuleSet::TwentyFortyEight);
+        for _ in 0..50 {
+            match ai.best_move(&s) {
+                Some(d) => {
+                    assert!(s.legal_moves().contains(&d));
+                    s.shift(d);
+                }
+                None => break,
+            }
+        }
+        assert!(s.score() > 0);
+    }
+}
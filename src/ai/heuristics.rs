@@ -0,0 +1,95 @@
+use crate::{model::grid::Grid, rules::Rules};
+
+// weights of the features a grid is evaluated on
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Heuristics {
+    pub score: f64,
+    // number of empty cells
+    pub empty: f64,
+    // number of adjacent tiles which could combine
+    pub merges: f64,
+    // ranks increasing or decreasing steadily along rows and columns, always <= 0
+    pub monotonicity: f64,
+}
+
+impl Default for Heuristics {
+    fn default() -> Heuristics {
+        Heuristics {
+            score: 1.0,
+            empty: 8.0,
+            merges: 4.0,
+            monotonicity: 2.0,
+        }
+    }
+}
+
+impl Heuristics {
+    pub fn evaluate(&self, rules: &dyn Rules, grid: &Grid) -> f64 {
+        self.score * grid.score(rules) as f64
+            + self.empty * grid.empty_cells() as f64
+            + self.merges * grid.merges(rules) as f64
+            + self.monotonicity * Self::monotonicity(rules, grid)
+    }
+
+    // penalizes the smaller of the increases and decreases in rank along each line
+    fn monotonicity(rules: &dyn Rules, grid: &Grid) -> f64 {
+        let rank = |e: &u32| rules.rank(*e) as f64;
+        let line = |ranks: Vec<f64>| {
+            let (inc, dec) = ranks.windows(2).fold((0.0, 0.0), |(inc, dec), w| {
+                if w[1] > w[0] {
+                    (inc + w[1] - w[0], dec)
+                } else {
+                    (inc, dec + w[0] - w[1])
+                }
+            });
+            f64::min(inc, dec)
+        };
+        let cols: f64 = grid
+            .matrix
+            .column_iter()
+            .map(|c| line(c.iter().map(rank).collect()))
+            .sum();
+        let rows: f64 = grid
+            .matrix
+            .row_iter()
+            .map(|r| line(r.iter().map(rank).collect()))
+            .sum();
+        -(cols + rows)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use nalgebra::Matrix4;
+
+    use crate::rules::threes::Threes;
+
+    use super::*;
+
+    #[test]
+    fn monotonicity_is_0_if_lines_are_sorted() {
+        let m = Matrix4::new(3, 6, 12, 24, 6, 12, 24, 48, 12, 24, 48, 96, 24, 48, 96, 192);
+        assert_eq!(
+            Heuristics::monotonicity(&Threes::default(), &Grid::from(m)),
+            0.0
+        );
+    }
+
+    #[test]
+    fn monotonicity_penalizes_zigzags() {
+        let m = Matrix4::new(3, 24, 3, 24, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0);
+        assert!(Heuristics::monotonicity(&Threes::default(), &Grid::from(m)) < 0.0);
+    }
+
+    #[test]
+    fn evaluate_prefers_empty_cells() {
+        let h = Heuristics::default();
+        let full = Matrix4::new(1, 3, 1, 3, 3, 1, 3, 1, 1, 3, 1, 3, 3, 1, 3, 1);
+        let mut sparse = full;
+        sparse[(0, 0)] = 0;
+        assert!(
+            h.evaluate(&Threes::default(), &Grid::from(sparse))
+                > h.evaluate(&Threes::default(), &Grid::from(full))
+        );
+    }
+}
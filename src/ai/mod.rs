@@ -0,0 +1,3 @@
+pub mod expectimax;
+pub mod heuristics;
+pub mod strategy;
@@ -0,0 +1,153 @@
+use std::{
+    io::{BufRead, BufReader, Write},
+    process::{Child, ChildStdin, ChildStdout, Command, Stdio},
+};
+
+use anyhow::{bail, Context, Result};
+use rand::seq::SliceRandom;
+
+use crate::{
+    model::direction::Direction,
+    rng::{self, GameRng},
+    state::State,
+};
+
+use super::{expectimax::Expectimax, heuristics::Heuristics};
+
+// picks the next move, none to give up
+pub trait Strategy {
+    fn choose(&mut self, state: &State) -> Result<Option<Direction>>;
+}
+
+impl Strategy for Expectimax {
+    fn choose(&mut self, state: &State) -> Result<Option<Direction>> {
+        Ok(self.best_move(state))
+    }
+}
+
+// a uniformly random legal move
+pub struct Random {
+    rng: GameRng,
+}
+
+impl Random {
+    pub fn new(seed: u64) -> Random {
+        Random {
+            rng: rng::from_seed(seed),
+        }
+    }
+}
+
+impl Strategy for Random {
+    fn choose(&mut self, state: &State) -> Result<Option<Direction>> {
+        Ok(state.legal_moves().choose(&mut self.rng).copied())
+    }
+}
+
+// the move with the best expected score right after it
+pub fn greedy() -> Expectimax {
+    Expectimax::new(1).heuristics(Heuristics {
+        score: 1.0,
+        empty: 0.0,
+        merges: 0.0,
+        monotonicity: 0.0,
+    })
+}
+
+// an external program, sent the position in notation on a line of its stdin and answering with
+// a move, U, D, L or R, on a line of its stdout
+pub struct Bot {
+    child: Child,
+    stdin: ChildStdin,
+    stdout: BufReader<ChildStdout>,
+}
+
+impl Bot {
+    // the command is split on whitespace
+    pub fn spawn(command: &str) -> Result<Bot> {
+        let mut parts = command.split_whitespace();
+        let program = parts.next().context("the bot command is empty")?;
+        let mut child = Command::new(program)
+            .args(parts)
+            .stdin(Stdio::piped())
+            .stdout(Stdio::piped())
+            .spawn()
+            .with_context(|| format!("failed to start bot `{}`", command))?;
+        let stdin = child.stdin.take().context("bot has no stdin")?;
+        let stdout = child.stdout.take().context("bot has no stdout")?;
+        Ok(Bot {
+            child,
+            stdin,
+            stdout: BufReader::new(stdout),
+        })
+    }
+}
+
+impl Strategy for Bot {
+    fn choose(&mut self, state: &State) -> Result<Option<Direction>> {
+        if state.legal_moves().is_empty() {
+            return Ok(None);
+        }
+        writeln!(self.stdin, "{}", state.notation()).context("failed to write to the bot")?;
+        self.stdin.flush().context("failed to write to the bot")?;
+        let mut line = String::new();
+        let read = self
+            .stdout
+            .read_line(&mut line)
+            .context("failed to read from the bot")?;
+        if read == 0 {
+            bail!("the bot exited");
+        }
+        let dir = line
+            .parse()
+            .with_context(|| format!("bot answered `{}` to {}", line.trim(), state.notation()))?;
+        Ok(Some(dir))
+    }
+}
+
+impl Drop for Bot {
+    fn drop(&mut self) {
+        let _ = self.child.kill();
+        let _ = self.child.wait();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn random_plays_legal_moves() {
+        let mut strategy = Random::new(7);
+        let mut s = State::new(7);
+        while let Some(d) = strategy.choose(&s).unwrap() {
+            assert!(s.legal_moves().contains(&d));
+            s.shift(d);
+        }
+        assert!(s.legal_moves().is_empty());
+    }
+
+    // sed stands in for a bot, hence unix only
+    #[test]
+    #[cfg(unix)]
+    fn bot_answers_are_parsed() {
+        // answers left to everything
+        let mut bot = Bot::spawn("sed -u s/.*/L/").unwrap();
+        let s = State::new(7);
+        assert_eq!(bot.choose(&s).unwrap(), Some(Direction::Left));
+        assert_eq!(bot.choose(&s).unwrap(), Some(Direction::Left));
+    }
+
+    #[test]
+    #[cfg(unix)]
+    fn bot_invalid_answers_are_errors() {
+        let mut bot = Bot::spawn("sed -u s/.*/X/").unwrap();
+        assert!(bot.choose(&State::new(7)).is_err());
+    }
+
+    #[test]
+    fn bot_that_cannot_start_is_an_error() {
+        assert!(Bot::spawn("threes-no-such-bot").is_err());
+        assert!(Bot::spawn("").is_err());
+    }
+}
@@ -0,0 +1,456 @@
+use std::{collections::BTreeMap, fmt, fs, io, path::PathBuf, time::Duration};
+
+use anyhow::{anyhow, bail, Context, Result};
+use clap::ValueEnum;
+use crossterm::event::KeyCode;
+use serde::Deserialize;
+use threes::{
+    history::UndoPolicy,
+    model::{direction::Direction, size::Size},
+};
+
+use crate::ui::{palette::ColorDepth, theme::Markers};
+
+// what a key does, actions are bound in the [keys] table of the config file
+#[derive(Debug, PartialEq, Eq, PartialOrd, Ord, Copy, Clone, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Action {
+    Up,
+    Down,
+    Left,
+    Right,
+    Undo,
+    Redo,
+    Restart,
+    Hint,
+    Autoplay,
+    Scores,
+    Position,
+    Save,
+    Load,
+    Theme,
+    Quit,
+}
+
+impl Action {
+    pub fn direction(self) -> Option<Direction> {
+        match self {
+            Action::Up => Some(Direction::Up),
+            Action::Down => Some(Direction::Down),
+            Action::Left => Some(Direction::Left),
+            Action::Right => Some(Direction::Right),
+            _ => None,
+        }
+    }
+}
+
+impl fmt::Display for Action {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let name = match self {
+            Action::Up => "up",
+            Action::Down => "down",
+            Action::Left => "left",
+            Action::Right => "right",
+            Action::Undo => "undo",
+            Action::Redo => "redo",
+            Action::Restart => "restart",
+            Action::Hint => "hint",
+            Action::Autoplay => "autoplay",
+            Action::Scores => "scores",
+            Action::Position => "position",
+            Action::Save => "save",
+            Action::Load => "load",
+            Action::Theme => "theme",
+            Action::Quit => "quit",
+        };
+        write!(f, "{}", name)
+    }
+}
+
+const DEFAULT_KEYS: [(Action, &[&str]); 15] = [
+    (Action::Up, &["up", "w"]),
+    (Action::Down, &["down", "s"]),
+    (Action::Left, &["left", "a"]),
+    (Action::Right, &["right", "d"]),
+    (Action::Undo, &["u"]),
+    (Action::Redo, &["y"]),
+    (Action::Restart, &["r"]),
+    (Action::Hint, &["?"]),
+    (Action::Autoplay, &["p"]),
+    (Action::Scores, &["t"]),
+    (Action::Position, &["n"]),
+    (Action::Save, &["S"]),
+    (Action::Load, &["L"]),
+    (Action::Theme, &["c"]),
+    (Action::Quit, &["q"]),
+];
+
+// keys written by name rather than as a single character
+const NAMED_KEYS: [(&str, KeyCode); 15] = [
+    ("up", KeyCode::Up),
+    ("down", KeyCode::Down),
+    ("left", KeyCode::Left),
+    ("right", KeyCode::Right),
+    ("space", KeyCode::Char(' ')),
+    ("enter", KeyCode::Enter),
+    ("esc", KeyCode::Esc),
+    ("tab", KeyCode::Tab),
+    ("backspace", KeyCode::Backspace),
+    ("delete", KeyCode::Delete),
+    ("insert", KeyCode::Insert),
+    ("home", KeyCode::Home),
+    ("end", KeyCode::End),
+    ("pageup", KeyCode::PageUp),
+    ("pagedown", KeyCode::PageDown),
+];
+
+// a single character is that key, case included, other keys are named, e.g. `up` or `f1`
+fn parse_key(name: &str) -> Result<KeyCode> {
+    let mut chars = name.chars();
+    if let (Some(c), None) = (chars.next(), chars.next()) {
+        return Ok(KeyCode::Char(c));
+    }
+    let lower = name.to_lowercase();
+    if let Some((_, code)) = NAMED_KEYS.iter().find(|(n, _)| *n == lower) {
+        return Ok(*code);
+    }
+    match lower.strip_prefix('f').and_then(|n| n.parse::<u8>().ok()) {
+        Some(n @ 1..=12) => Ok(KeyCode::F(n)),
+        _ => bail!(
+            "unknown key `{}`, expected a character or a name such as up, enter or f1",
+            name
+        ),
+    }
+}
+
+fn key_name(code: KeyCode) -> String {
+    match code {
+        KeyCode::Up => "↑".to_string(),
+        KeyCode::Down => "↓".to_string(),
+        KeyCode::Left => "←".to_string(),
+        KeyCode::Right => "→".to_string(),
+        KeyCode::F(n) => format!("f{}", n),
+        KeyCode::Char(c) if c != ' ' => c.to_string(),
+        code => NAMED_KEYS
+            .iter()
+            .find(|(_, c)| *c == code)
+            .map_or("?", |(n, _)| n)
+            .to_string(),
+    }
+}
+
+// either `quit = "q"` or `quit = ["q", "esc"]`
+#[derive(Debug, Deserialize)]
+#[serde(untagged)]
+enum OneOrMany {
+    One(String),
+    Many(Vec<String>),
+}
+
+impl OneOrMany {
+    fn names(&self) -> &[String] {
+        match self {
+            OneOrMany::One(name) => std::slice::from_ref(name),
+            OneOrMany::Many(names) => names,
+        }
+    }
+}
+
+// the keys bound to every action, no key is bound to two of them
+#[derive(Debug, Clone)]
+pub struct Keys {
+    bindings: Vec<(Action, Vec<KeyCode>)>,
+}
+
+impl Keys {
+    // the default keys, with the actions given rebound
+    fn with(rebound: &BTreeMap<Action, OneOrMany>) -> Result<Keys> {
+        let mut bindings: Vec<(Action, Vec<KeyCode>)> = vec![];
+        for (action, defaults) in DEFAULT_KEYS {
+            let codes = match rebound.get(&action) {
+                Some(keys) => keys
+                    .names()
+                    .iter()
+                    .map(|name| parse_key(name))
+                    .collect::<Result<Vec<_>>>()
+                    .with_context(|| format!("invalid value for `keys.{}`", action))?,
+                None => defaults
+                    .iter()
+                    .map(|name| parse_key(name))
+                    .collect::<Result<_>>()?,
+            };
+            if action == Action::Quit && codes.is_empty() {
+                bail!("invalid value for `keys.quit`: at least one key is needed to quit");
+            }
+            for code in &codes {
+                if let Some((other, _)) = bindings.iter().find(|(_, c)| c.contains(code)) {
+                    // the error points at the action which was rebound
+                    let (key, other) = if rebound.contains_key(&action) {
+                        (action, *other)
+                    } else {
+                        (*other, action)
+                    };
+                    bail!(
+                        "invalid value for `keys.{}`: {} is already bound to {}",
+                        key,
+                        key_name(*code),
+                        other
+                    );
+                }
+            }
+            bindings.push((action, codes));
+        }
+        Ok(Keys { bindings })
+    }
+
+    pub fn action(&self, code: KeyCode) -> Option<Action> {
+        self.bindings
+            .iter()
+            .find(|(_, codes)| codes.contains(&code))
+            .map(|(action, _)| *action)
+    }
+
+    // the first key bound to an action, as shown in the help
+    pub fn label(&self, action: Action) -> String {
+        self.bindings
+            .iter()
+            .find(|(a, _)| *a == action)
+            .and_then(|(_, codes)| codes.first())
+            .map_or("-".to_string(), |code| key_name(*code))
+    }
+}
+
+impl Default for Keys {
+    fn default() -> Keys {
+        Keys::with(&BTreeMap::new()).expect("the default keys are valid")
+    }
+}
+
+// the config file as written, every value is optional
+#[derive(Debug, Default, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+struct File {
+    theme: Option<String>,
+    colors: Option<String>,
+    markers: Option<Markers>,
+    size: Option<String>,
+    undo_depth: Option<usize>,
+    animation: AnimationFile,
+    keys: BTreeMap<Action, OneOrMany>,
+}
+
+#[derive(Debug, Default, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+struct AnimationFile {
+    fps: Option<u32>,
+    duration_ms: Option<u64>,
+    reduced_motion: Option<bool>,
+}
+
+// preferences read from the config file, the command line overrides them
+#[derive(Debug, Clone)]
+pub struct Config {
+    pub keys: Keys,
+    // the name of a bundled theme or of one in the themes directory
+    pub theme: String,
+    // detected from the terminal if none
+    pub colors: Option<ColorDepth>,
+    // the theme's own if none
+    pub markers: Option<Markers>,
+    // new games are played on boards of this size unless another one is given
+    pub size: Size,
+    pub undo_policy: UndoPolicy,
+    pub fps: u32,
+    // how long a move takes to play out
+    pub animation: Duration,
+    pub reduced_motion: bool,
+}
+
+impl Default for Config {
+    fn default() -> Config {
+        Config {
+            keys: Keys::default(),
+            theme: "classic".to_string(),
+            colors: None,
+            markers: None,
+            size: Size::default(),
+            undo_policy: UndoPolicy::Unlimited,
+            fps: 60,
+            animation: Duration::from_millis(160),
+            reduced_motion: false,
+        }
+    }
+}
+
+impl Config {
+    // $XDG_CONFIG_HOME/threes/config.toml on linux
+    pub fn default_path() -> Option<PathBuf> {
+        dirs::config_dir().map(|d| d.join("threes").join("config.toml"))
+    }
+
+    // the defaults if there is no config file
+    pub fn load() -> Result<Config> {
+        let Some(path) = Self::default_path() else {
+            return Ok(Config::default());
+        };
+        match fs::read_to_string(&path) {
+            Ok(s) => {
+                Config::parse(&s).with_context(|| format!("invalid config {}", path.display()))
+            }
+            Err(e) if e.kind() == io::ErrorKind::NotFound => Ok(Config::default()),
+            Err(e) => Err(e).with_context(|| format!("failed to read {}", path.display())),
+        }
+    }
+
+    pub fn parse(s: &str) -> Result<Config> {
+        let file: File = toml::from_str(s)?;
+        let mut config = Config {
+            keys: Keys::with(&file.keys)?,
+            ..Config::default()
+        };
+        if let Some(name) = file.theme {
+            config.theme = name;
+        }
+        if let Some(colors) = file.colors {
+            let depth = ColorDepth::from_str(&colors, true).map_err(|_| {
+                anyhow!(
+                    "invalid value for `colors`: expected truecolor, 256, 16 or mono, found `{}`",
+                    colors
+                )
+            })?;
+            config.colors = Some(depth);
+        }
+        config.markers = file.markers;
+        if let Some(size) = file.size {
+            config.size = size.parse().context("invalid value for `size`")?;
+        }
+        config.undo_policy = match file.undo_depth {
+            Some(0) => UndoPolicy::Disabled,
+            Some(depth) => UndoPolicy::Limited(depth),
+            None => UndoPolicy::Unlimited,
+        };
+        match file.animation.fps {
+            Some(0) => bail!("invalid value for `animation.fps`: expected at least 1"),
+            Some(fps) => config.fps = fps,
+            None => (),
+        }
+        if let Some(ms) = file.animation.duration_ms {
+            config.animation = Duration::from_millis(ms);
+        }
+        if let Some(reduced_motion) = file.animation.reduced_motion {
+            config.reduced_motion = reduced_motion;
+        }
+        Ok(config)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn error(s: &str) -> String {
+        format!("{:#}", Config::parse(s).unwrap_err())
+    }
+
+    #[test]
+    fn an_empty_file_keeps_the_defaults() {
+        let config = Config::parse("").unwrap();
+        assert_eq!(config.size, Size::default());
+        assert_eq!(config.undo_policy, UndoPolicy::Unlimited);
+        assert_eq!(config.fps, 60);
+        assert_eq!(config.keys.action(KeyCode::Char('w')), Some(Action::Up));
+        assert_eq!(config.keys.action(KeyCode::Left), Some(Action::Left));
+        assert_eq!(config.keys.action(KeyCode::Char('Q')), None);
+        assert_eq!(config.keys.label(Action::Save), "S");
+    }
+
+    #[test]
+    fn parses_every_preference() {
+        let config = Config::parse(
+            r#"
+            theme = "classic"
+            colors = "256"
+            markers = "borders"
+            size = "5x4"
+            undo_depth = 0
+
+            [animation]
+            fps = 30
+            duration_ms = 80
+            reduced_motion = true
+            "#,
+        )
+        .unwrap();
+        assert_eq!(config.size, Size::new(5, 4).unwrap());
+        assert_eq!(config.colors, Some(ColorDepth::Ansi256));
+        assert_eq!(config.markers, Some(Markers::Borders));
+        assert_eq!(config.undo_policy, UndoPolicy::Disabled);
+        assert_eq!(config.fps, 30);
+        assert_eq!(config.animation, Duration::from_millis(80));
+        assert!(config.reduced_motion);
+    }
+
+    #[test]
+    fn binds_vim_keys() {
+        let keys = Config::parse(
+            r#"
+            [keys]
+            left = ["left", "h"]
+            down = ["down", "j"]
+            up = ["up", "k"]
+            right = ["right", "l"]
+            quit = ["q", "esc"]
+            "#,
+        )
+        .unwrap()
+        .keys;
+        assert_eq!(keys.action(KeyCode::Char('h')), Some(Action::Left));
+        assert_eq!(keys.action(KeyCode::Char('l')), Some(Action::Right));
+        assert_eq!(keys.action(KeyCode::Char('?')), Some(Action::Hint));
+        assert_eq!(keys.action(KeyCode::Esc), Some(Action::Quit));
+        // rebinding an action replaces its default keys
+        assert_eq!(keys.action(KeyCode::Char('a')), None);
+        assert_eq!(keys.label(Action::Left), "←");
+    }
+
+    #[test]
+    fn a_key_bound_twice_points_at_the_rebound_action() {
+        assert_eq!(
+            error("[keys]\nleft = \"?\""),
+            "invalid value for `keys.left`: ? is already bound to hint"
+        );
+        assert_eq!(
+            error("[keys]\nquit = \"u\""),
+            "invalid value for `keys.quit`: u is already bound to undo"
+        );
+    }
+
+    #[test]
+    fn invalid_values_name_their_key() {
+        assert!(error("[keys]\nup = \"upward\"")
+            .starts_with("invalid value for `keys.up`: unknown key `upward`"));
+        assert_eq!(
+            error("size = \"9x4\""),
+            "invalid value for `size`: expected between 2 and 8 columns, found 9"
+        );
+        assert!(error("colors = \"8\"").starts_with("invalid value for `colors`"));
+        assert!(error("[animation]\nfps = 0").starts_with("invalid value for `animation.fps`"));
+        assert!(error("[keys]\nquit = []").starts_with("invalid value for `keys.quit`"));
+        // the file's own errors come with their line
+        assert!(error("fsp = 30").contains("unknown field `fsp`"));
+        assert!(error("markers = \"dots\"").contains("unknown variant `dots`"));
+        assert!(error("[keys]\njump = \"j\"").contains("line 2"));
+        assert!(error("[animation]\nfps = \"fast\"").contains("line 2"));
+    }
+
+    #[test]
+    fn parses_named_keys() {
+        assert_eq!(parse_key("Enter").unwrap(), KeyCode::Enter);
+        assert_eq!(parse_key("f5").unwrap(), KeyCode::F(5));
+        assert_eq!(parse_key("f").unwrap(), KeyCode::Char('f'));
+        assert!(parse_key("f13").is_err());
+        for (name, code) in NAMED_KEYS {
+            assert_eq!(parse_key(name).unwrap(), code);
+        }
+    }
+}
@@ -1,78 +1,298 @@
-use std::{
-    io::{stdout, Stdout},
-    ops::ControlFlow,
-    time::Duration,
-};
+use std::{ops::ControlFlow, path::PathBuf, time::Duration};
 
-use anyhow::{Context, Result};
-use crossterm::{
-    event::{self, Event, KeyCode},
-    terminal::{disable_raw_mode, enable_raw_mode, EnterAlternateScreen, LeaveAlternateScreen},
-    ExecutableCommand,
-};
-use rand::{rngs::OsRng, Rng};
+use anyhow::Result;
+use crossterm::event::{self, Event, KeyCode};
 use ratatui::{
-    backend::CrosstermBackend,
-    layout::{Alignment, Direction, Rect},
-    style::Stylize,
-    text::Line,
+    backend::Backend,
+    layout::{Alignment, Constraint, Direction, Layout},
+    style::{Modifier, Style, Stylize},
+    text::{Line, Span},
     widgets::{Block, Borders, Clear, Paragraph},
     Frame, Terminal,
 };
 
-use crate::{
+use threes::{
+    ai::expectimax::Expectimax,
+    history::UndoPolicy,
+    model::{direction::Direction as Move, size::Size},
+    replay, rng,
+    rules::RuleSet,
+    save::{Saves, Slot},
+    scores::{Entry, HighScores},
     state::State,
+};
+
+use crate::{
+    config::{Action, Keys},
     ui::{
-        layouts::{
-            popup_layout, GAME_LAYOUT_H, GAME_LAYOUT_V, HORIZONTAL_SEP, MAIN_LAYOUT, ROW_LAYOUT,
-        },
+        animation::Animation,
+        board::Board,
+        layouts::{centered_rect, game_layout, HORIZONTAL_SEP, MAIN_LAYOUT},
+        scoreboard::Scoreboard,
         square::Square,
-        theme::OTHER_THEME,
+        terminal::with_terminal,
+        theme::Themes,
     },
 };
 
+pub struct Options {
+    // a new game is started with this seed instead of resuming the last one
+    pub seed: Option<u64>,
+    // a new game is started on a board of this size instead of resuming the last one
+    pub size: Option<Size>,
+    // the size of new games otherwise
+    pub default_size: Size,
+    // a new game is started with these rules instead of resuming the last one
+    pub rules: Option<RuleSet>,
+    // only applies to new games, resumed ones keep theirs
+    pub undo_policy: UndoPolicy,
+    // number of moves the autoplay and hints look ahead
+    pub ai_depth: u32,
+    // frames per second moves are animated at
+    pub fps: u32,
+    // how long a move takes to play out
+    pub animation: Duration,
+    // moves are drawn at once rather than animated
+    pub reduced_motion: bool,
+    pub keys: Keys,
+    pub themes: Themes,
+}
+
+#[derive(Debug, PartialEq, Eq, Copy, Clone)]
+enum SlotAction {
+    Save,
+    Load,
+}
+
 pub struct Game {
     state: State,
+    undo_policy: UndoPolicy,
+    saves: Option<Saves>,
+    // waiting for a slot number
+    pending: Option<SlotAction>,
+    status: Option<String>,
+    scores: HighScores,
+    // none if the scores can't be persisted
+    scores_path: Option<PathBuf>,
+    show_scores: bool,
+    ai: Expectimax,
+    autoplay: bool,
+    // the ai's evaluation of the current position, cleared as soon as it changes
+    hint: Option<Vec<(Move, f64)>>,
+    // the last move while it plays out
+    animation: Option<Animation>,
+    fps: u32,
+    animation_duration: Duration,
+    reduced_motion: bool,
+    keys: Keys,
+    themes: Themes,
 }
 
 impl Game {
-    fn new(r: &mut OsRng) -> Game {
-        Game {
-            state: State::from_base_values(r, [4, 2, 2, 2]),
+    fn new(options: Options) -> Game {
+        let mut game = Game {
+            state: Self::new_state(
+                options.seed,
+                options.size.unwrap_or(options.default_size),
+                options.rules.unwrap_or_default(),
+                options.undo_policy,
+            ),
+            undo_policy: options.undo_policy,
+            saves: None,
+            pending: None,
+            status: None,
+            scores: HighScores::default(),
+            scores_path: None,
+            show_scores: false,
+            ai: Expectimax::new(options.ai_depth),
+            autoplay: false,
+            hint: None,
+            animation: None,
+            fps: options.fps.max(1),
+            animation_duration: options.animation,
+            reduced_motion: options.reduced_motion,
+            keys: options.keys,
+            themes: options.themes,
+        };
+        match Saves::default_dir() {
+            Ok(dir) => game.saves = Some(Saves::new(dir)),
+            Err(e) => game.status = Some(format!("{:#}", e)),
+        }
+        if options.seed.is_none() && options.size.is_none() && options.rules.is_none() {
+            game.resume();
         }
+        game.load_scores();
+        game
+    }
+
+    fn new_state(seed: Option<u64>, size: Size, rules: RuleSet, undo_policy: UndoPolicy) -> State {
+        State::with_rules(seed.unwrap_or_else(rng::random_seed), size, rules)
+            .undo_policy(undo_policy)
+    }
+
+    // every rule set has its own high scores
+    fn load_scores(&mut self) {
+        self.scores = HighScores::default();
+        self.scores_path = None;
+        match HighScores::default_path(self.state.rules())
+            .and_then(|path| HighScores::read(&path).map(|scores| (path, scores)))
+        {
+            Ok((path, scores)) => {
+                self.scores = scores;
+                self.scores_path = Some(path);
+            }
+            Err(e) => self.status = Some(format!("{:#}", e)),
+        }
+    }
+
+    pub fn run(options: Options) -> Result<()> {
+        let mut game = Game::new(options);
+        with_terminal(|terminal| game.event_loop(terminal))?;
+        // both are attempted so that failing to write the replay or the scores never loses the
+        // unfinished game
+        let saved = game.autosave();
+        let left = game.leave();
+        saved.and(left)
     }
 
-    pub fn run() -> Result<()> {
-        let mut terminal = init_terminal()?;
-        let mut r = OsRng;
-        let mut game = Game::new(&mut r);
+    fn event_loop(&mut self, terminal: &mut Terminal<impl Backend>) -> Result<()> {
         loop {
-            let _ = terminal.draw(|frame| game.ui(frame));
-            if !event::poll(Duration::from_millis(100))? {
+            let _ = terminal.draw(|frame| {
+                self.ui(frame);
+                self.themes.adapt(frame.buffer_mut());
+            });
+            if self.animation.as_ref().is_some_and(Animation::is_done) {
+                self.animation = None;
+            }
+            if self.autoplay && self.animation.is_none() {
+                self.autoplay_move();
+            }
+            let timeout = if self.animation.is_some() {
+                Duration::from_secs(1) / self.fps
+            } else {
+                Duration::from_millis(100)
+            };
+            if !event::poll(timeout)? {
                 continue;
             }
-            match event::read()? {
-                Event::Key(key) => {
-                    if key.kind != event::KeyEventKind::Press {
-                        continue;
-                    }
-                    if game.handle_key_event(&mut r, key).is_break() {
-                        break;
-                    }
+            if let Event::Key(key) = event::read()? {
+                if key.kind != event::KeyEventKind::Press {
+                    continue;
                 }
-                _ => (),
+                if self.handle_key_event(key).is_break() {
+                    return Ok(());
+                }
+            }
+        }
+    }
+
+    // games are recorded when they are left, a resumed game overwrites its previous recording,
+    // finished ones also enter the high scores
+    fn leave(&mut self) -> Result<()> {
+        if self.state.moves() == 0 {
+            return Ok(());
+        }
+        let path = replay::default_dir()?.join(format!("{}.json", self.state.seed()));
+        replay::write(&path, &self.state.replay())?;
+        match &self.scores_path {
+            Some(path) if self.state.is_game_over() => {
+                self.scores.insert(Entry::now(&self.state));
+                self.scores.write(path)
             }
+            _ => Ok(()),
+        }
+    }
+
+    fn autoplay_move(&mut self) {
+        match self.ai.best_move(&self.state) {
+            Some(dir) => {
+                self.shift(dir);
+                self.hint = None;
+            }
+            None => self.autoplay = false,
+        }
+    }
+
+    // animated unless nothing moved
+    fn shift(&mut self, dir: Move) {
+        let outcome = self.state.shift(dir);
+        if !self.reduced_motion && !outcome.is_noop() {
+            self.animation = Some(Animation::new(
+                outcome,
+                self.animation_duration,
+                self.themes.current(),
+            ));
         }
-        restore_terminal()
     }
 
-    fn ui(&mut self, frame: &mut Frame) -> () {
+    // the scores as they'd be if the current game entered them
+    fn scores_with_current(&self) -> (HighScores, Option<usize>) {
+        let mut scores = self.scores.clone();
+        let rank = scores.insert(Entry::now(&self.state));
+        (scores, rank)
+    }
+
+    fn resume(&mut self) {
+        if let Some(saves) = &self.saves {
+            match saves.load(Slot::Auto) {
+                Ok(Some(state)) => {
+                    self.state = state;
+                    self.status = Some("resumed the last game".to_string());
+                }
+                Ok(None) => (),
+                Err(e) => self.status = Some(format!("{:#}, starting a new game", e)),
+            }
+        }
+    }
+
+    // finished games are not worth resuming
+    fn autosave(&self) -> Result<()> {
+        match &self.saves {
+            Some(saves) if self.state.is_game_over() => saves.delete(Slot::Auto),
+            Some(saves) => saves.save(Slot::Auto, &self.state).map(|_| ()),
+            None => Ok(()),
+        }
+    }
+
+    fn slot_action(&mut self, action: SlotAction, slot: Slot) {
+        let Some(saves) = &self.saves else {
+            return;
+        };
+        let Slot::Numbered(n) = slot else {
+            return;
+        };
+        self.status = Some(match action {
+            SlotAction::Save => match saves.save(slot, &self.state) {
+                Ok(_) => format!("saved to slot {}", n),
+                Err(e) => format!("{:#}", e),
+            },
+            SlotAction::Load => match saves.load(slot) {
+                Ok(Some(state)) => {
+                    let rules = self.state.rules();
+                    self.state = state;
+                    if self.state.rules() != rules {
+                        self.load_scores();
+                    }
+                    format!("loaded slot {}", n)
+                }
+                Ok(None) => format!("slot {} is empty", n),
+                Err(e) => format!("{:#}", e),
+            },
+        });
+    }
+
+    fn ui(&mut self, frame: &mut Frame) {
         let score = self.state.score();
+        let theme = self.themes.current();
 
         let main_layout = MAIN_LAYOUT.split(frame.size());
         frame.render_widget(
             Paragraph::new(vec![
-                Line::from("threes".dark_gray()).alignment(Alignment::Left)
+                Line::from(
+                    format!("{} - seed {}", self.state.rules(), self.state.seed()).dark_gray(),
+                )
+                .alignment(Alignment::Left),
+                Line::from(self.status_line().dark_gray()).alignment(Alignment::Left),
             ]),
             main_layout[0],
         );
@@ -80,91 +300,222 @@ impl Game {
         // next tile
         let next_tile_block = Block::new()
             .borders(Borders::ALL)
+            .border_style(theme.border_style())
             .title("next tile".dark_gray());
-        let next_tile_widget = Square::from_elem(self.state.tile.current()).block(next_tile_block);
+        let next_tile_widget =
+            Square::preview(&self.state.tile().preview(), theme).block(next_tile_block);
         frame.render_widget(next_tile_widget, HORIZONTAL_SEP.split(main_layout[1])[0]);
 
         // score
         let score_block = Block::new()
             .borders(Borders::ALL)
+            .border_style(theme.border_style())
             .title("score".dark_gray());
-        let next_tile_widget = Square::from_elem(score)
-            .theme(OTHER_THEME)
+        let next_tile_widget = Square::new(score.to_string())
+            .colors(theme.panel)
             .block(score_block);
         frame.render_widget(next_tile_widget, HORIZONTAL_SEP.split(main_layout[1])[1]);
 
         // game
+        let legal = self.state.legal_moves();
         let game_block = Block::new()
             .borders(Borders::ALL)
-            .title("use ← 	↑ 	→ 	↓ to play, q to quit, u to undo".dark_gray());
-        let game_area = GAME_LAYOUT_H.split(GAME_LAYOUT_V.split(main_layout[2])[0])[0];
+            .border_style(theme.border_style())
+            .title(match &self.hint {
+                Some(hint) => hint_line(hint),
+                None => moves_line(&legal, &self.keys),
+            });
+        let size = self.state.size();
+        let board_layout = game_layout(size.rows, Direction::Vertical).split(main_layout[2]);
+        let game_area = game_layout(size.cols, Direction::Horizontal).split(board_layout[0])[0];
         frame.render_widget(game_block, game_area);
 
+        // other keys
+        let keys = &self.keys;
+        let mut help = format!(
+            "{}/{} save/load, {} autoplay, {} high scores, {} position, {} theme",
+            keys.label(Action::Save),
+            keys.label(Action::Load),
+            keys.label(Action::Autoplay),
+            keys.label(Action::Scores),
+            keys.label(Action::Position),
+            keys.label(Action::Theme)
+        );
+        if self.undo_policy != UndoPolicy::Disabled {
+            help = format!(
+                "{}/{} undo/redo, {}",
+                keys.label(Action::Undo),
+                keys.label(Action::Redo),
+                help
+            );
+        }
+        frame.render_widget(Paragraph::new(help.dark_gray()), board_layout[1]);
+
         // game
-        let row_layout = ROW_LAYOUT.split(main_layout[2]);
-        let game_areas = row_layout
-            .iter()
-            .flat_map(|row| {
-                HORIZONTAL_SEP
-                    .split(*row)
-                    .iter()
-                    .copied()
-                    .take(4) // ignore min 0
-                    .collect::<Vec<_>>()
-            })
-            .collect::<Vec<_>>();
-        for i in 0..=3 {
-            for j in 0..=3 {
-                let elem = self.state.grid.matrix[(i, j)];
-                frame.render_widget(Square::from_elem(elem), game_areas[i * 4 + j])
-            }
+        match &self.animation {
+            Some(animation) => frame.render_widget(animation, main_layout[2]),
+            None => frame.render_widget(Board::new(self.state.grid(), theme), main_layout[2]),
         }
 
-        if self.state.game_over {
-            let block = Block::default().title("game over").borders(Borders::ALL);
-            let area = centered_rect(40, 20, frame.size());
+        if self.state.is_game_over() {
+            let block = Block::default()
+                .title("game over")
+                .borders(Borders::ALL)
+                .border_style(theme.border_style());
+            let area = centered_rect(60, 50, frame.size());
             frame.render_widget(Clear, area); //this clears out the background
-            let text = format!("your score is {}, q to quit, r to restart", score);
-            let paragraph = Paragraph::new(text.dark_gray());
-            frame.render_widget(paragraph.block(block), area);
-        }
-    }
-
-    fn handle_key_event<R: Rng + ?Sized>(
-        &mut self,
-        r: &mut R,
-        key: event::KeyEvent,
-    ) -> ControlFlow<()> {
-        if let Some(dir) = crate::model::direction::Direction::from_key_code(key.code) {
-            self.state.shift(r, dir);
-        } else if key.code == KeyCode::Char('u') {
-            self.state.shift_back();
-        } else if self.state.game_over && key.code == KeyCode::Char('r') {
-            self.state = State::from_base_values(r, [4, 2, 2, 2]);
-        } else if key.code == KeyCode::Char('q') {
+            let (scores, rank) = self.scores_with_current();
+            let ranked = match rank {
+                Some(r) => format!("rank {}", r + 1),
+                None => "not ranked".to_string(),
+            };
+            let mut text = format!(
+                "your score is {} ({}), {} to quit, {} to restart",
+                score,
+                ranked,
+                keys.label(Action::Quit),
+                keys.label(Action::Restart)
+            );
+            if self.state.can_undo() {
+                text = format!("{}, {} to undo", text, keys.label(Action::Undo));
+            }
+            let inner = block.inner(area);
+            frame.render_widget(block, area);
+            let popup_layout = Layout::new(
+                Direction::Vertical,
+                [Constraint::Length(2), Constraint::Min(0)],
+            )
+            .split(inner);
+            frame.render_widget(Paragraph::new(text.dark_gray()), popup_layout[0]);
+            frame.render_widget(Scoreboard::new(&scores).highlight(rank), popup_layout[1]);
+        } else if self.show_scores {
+            let block = Block::default()
+                .title(format!(
+                    "high scores, {} to close",
+                    keys.label(Action::Scores)
+                ))
+                .borders(Borders::ALL)
+                .border_style(theme.border_style());
+            let area = centered_rect(60, 50, frame.size());
+            frame.render_widget(Clear, area);
+            frame.render_widget(Scoreboard::new(&self.scores).block(block), area);
+        }
+    }
+
+    fn status_line(&self) -> String {
+        match self.pending {
+            Some(SlotAction::Save) => "save to slot 1-9, any other key to cancel".to_string(),
+            Some(SlotAction::Load) => "load slot 1-9, any other key to cancel".to_string(),
+            None if self.autoplay => {
+                format!("autoplaying, {} to stop", self.keys.label(Action::Autoplay))
+            }
+            None => self.status.clone().unwrap_or_default(),
+        }
+    }
+
+    fn handle_key_event(&mut self, key: event::KeyEvent) -> ControlFlow<()> {
+        if let Some(action) = self.pending.take() {
+            if let KeyCode::Char(c @ '1'..='9') = key.code {
+                self.slot_action(action, Slot::Numbered(c as u8 - b'0'));
+            }
+            return ControlFlow::Continue(());
+        }
+        self.status = None;
+        // a key cuts the last move short rather than waiting for it
+        self.animation = None;
+        let action = self.keys.action(key.code);
+        if action == Some(Action::Hint) {
+            self.hint = Some(self.ai.evaluate(&self.state));
+            return ControlFlow::Continue(());
+        }
+        self.hint = None;
+        let Some(action) = action else {
+            return ControlFlow::Continue(());
+        };
+        if let Some(dir) = action.direction() {
+            self.shift(dir);
+        } else if action == Action::Undo {
+            self.state.undo();
+        } else if action == Action::Redo {
+            self.state.redo();
+        } else if self.state.is_game_over() && action == Action::Restart {
+            if let Err(e) = self.leave() {
+                self.status = Some(format!("{:#}", e));
+            }
+            // restarts on the same board
+            self.state = Self::new_state(
+                None,
+                self.state.size(),
+                self.state.rules(),
+                self.undo_policy,
+            );
+        } else if action == Action::Autoplay {
+            self.autoplay = !self.autoplay;
+        } else if action == Action::Scores {
+            self.show_scores = !self.show_scores;
+        } else if action == Action::Theme {
+            self.status = Some(format!("theme {}", self.themes.cycle().name));
+        } else if action == Action::Position {
+            self.status = Some(self.state.notation());
+        } else if self.saves.is_some() && action == Action::Save {
+            self.pending = Some(SlotAction::Save);
+        } else if self.saves.is_some() && action == Action::Load {
+            self.pending = Some(SlotAction::Load);
+        } else if action == Action::Quit {
             return ControlFlow::Break(());
         }
         ControlFlow::Continue(())
     }
 }
 
-fn centered_rect(percent_x: u16, percent_y: u16, r: Rect) -> Rect {
-    let popup_layout_v = popup_layout(percent_y, Direction::Vertical).split(r);
-    popup_layout(percent_x, Direction::Horizontal).split(popup_layout_v[1])[1]
+fn arrow(dir: Move) -> &'static str {
+    match dir {
+        Move::Up => "↑",
+        Move::Down => "↓",
+        Move::Left => "←",
+        Move::Right => "→",
+    }
 }
 
-fn init_terminal() -> Result<Terminal<CrosstermBackend<Stdout>>> {
-    enable_raw_mode().context("failed to enable raw mode")?;
-    stdout()
-        .execute(EnterAlternateScreen)
-        .context("failed to enter alternate mode")?;
-    Terminal::new(CrosstermBackend::new(stdout())).context("terminal creation failed")
+// the arrows of the moves which are not legal are dimmed
+fn moves_line(legal: &[Move], keys: &Keys) -> Line<'static> {
+    let mut spans = vec![Span::from("use ").dark_gray()];
+    for dir in [Move::Left, Move::Up, Move::Right, Move::Down] {
+        let span = Span::from(format!("{} ", arrow(dir)));
+        if legal.contains(&dir) {
+            spans.push(span.bold());
+        } else {
+            spans.push(span.dark_gray().dim());
+        }
+    }
+    spans.push(
+        Span::from(format!(
+            "to play, {} for a hint, {} to quit",
+            keys.label(Action::Hint),
+            keys.label(Action::Quit)
+        ))
+        .dark_gray(),
+    );
+    Line::from(spans)
 }
 
-fn restore_terminal() -> Result<()> {
-    disable_raw_mode().context("failed to disable raw mode")?;
-    stdout()
-        .execute(LeaveAlternateScreen)
-        .context("failed to leave alternate screen")?;
-    Ok(())
+// every direction with its evaluation, the best one highlighted, illegal ones dimmed
+fn hint_line(hint: &[(Move, f64)]) -> Line<'static> {
+    let best = hint
+        .iter()
+        .max_by(|(_, a), (_, b)| a.total_cmp(b))
+        .map(|(d, _)| *d);
+    let mut spans = vec![Span::from("hint: ").dark_gray()];
+    for dir in [Move::Left, Move::Up, Move::Right, Move::Down] {
+        let span = match hint.iter().find(|(d, _)| *d == dir) {
+            Some((_, v)) => Span::from(format!(" {} {:.0} ", arrow(dir), v)),
+            None => Span::from(format!(" {} - ", arrow(dir))).dim(),
+        };
+        if Some(dir) == best {
+            spans.push(span.style(Style::new().add_modifier(Modifier::REVERSED | Modifier::BOLD)));
+        } else {
+            spans.push(span.dark_gray());
+        }
+    }
+    Line::from(spans)
 }
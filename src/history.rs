@@ -0,0 +1,142 @@
+use std::collections::VecDeque;
+
+use serde::{Deserialize, Serialize};
+
+#[derive(Debug, PartialEq, Eq, Copy, Clone, Serialize, Deserialize)]
+pub enum UndoPolicy {
+    Disabled,
+    // keeps at most this many moves
+    Limited(usize),
+    Unlimited,
+}
+
+impl UndoPolicy {
+    fn depth(self) -> Option<usize> {
+        match self {
+            UndoPolicy::Disabled => Some(0),
+            UndoPolicy::Limited(depth) => Some(depth),
+            UndoPolicy::Unlimited => None,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct History<T> {
+    undo: VecDeque<T>,
+    redo: Vec<T>,
+    policy: UndoPolicy,
+}
+
+impl<T> History<T> {
+    pub fn new(policy: UndoPolicy) -> History<T> {
+        History {
+            undo: VecDeque::new(),
+            redo: Vec::new(),
+            policy,
+        }
+    }
+
+    pub fn policy(&self) -> UndoPolicy {
+        self.policy
+    }
+
+    // to be called with the value before it is changed, a new change invalidates what was undone
+    pub fn record(&mut self, past: T) {
+        self.redo.clear();
+        if self.policy.depth() == Some(0) {
+            return;
+        }
+        self.undo.push_back(past);
+        if let Some(depth) = self.policy.depth() {
+            while self.undo.len() > depth {
+                self.undo.pop_front();
+            }
+        }
+    }
+
+    // swaps the current value for the previous one
+    pub fn undo(&mut self, current: T) -> Result<T, T> {
+        match self.undo.pop_back() {
+            Some(past) => {
+                self.redo.push(current);
+                Ok(past)
+            }
+            None => Err(current),
+        }
+    }
+
+    // swaps the current value for the last undone one
+    pub fn redo(&mut self, current: T) -> Result<T, T> {
+        match self.redo.pop() {
+            Some(future) => {
+                self.undo.push_back(current);
+                Ok(future)
+            }
+            None => Err(current),
+        }
+    }
+
+    pub fn can_undo(&self) -> bool {
+        !self.undo.is_empty()
+    }
+
+    pub fn can_redo(&self) -> bool {
+        !self.redo.is_empty()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn undo_goes_back_as_far_as_recorded() {
+        let mut h = History::new(UndoPolicy::Unlimited);
+        h.record(0);
+        h.record(1);
+        assert_eq!(h.undo(2), Ok(1));
+        assert_eq!(h.undo(1), Ok(0));
+        assert_eq!(h.undo(0), Err(0));
+    }
+
+    #[test]
+    fn redo_replays_undone_values() {
+        let mut h = History::new(UndoPolicy::Unlimited);
+        h.record(0);
+        h.record(1);
+        assert_eq!(h.undo(2), Ok(1));
+        assert_eq!(h.undo(1), Ok(0));
+        assert_eq!(h.redo(0), Ok(1));
+        assert_eq!(h.redo(1), Ok(2));
+        assert_eq!(h.redo(2), Err(2));
+    }
+
+    #[test]
+    fn record_clears_redo() {
+        let mut h = History::new(UndoPolicy::Unlimited);
+        h.record(0);
+        assert_eq!(h.undo(1), Ok(0));
+        h.record(0);
+        assert!(!h.can_redo());
+        assert_eq!(h.undo(3), Ok(0));
+    }
+
+    #[test]
+    fn limited_drops_oldest_values() {
+        let mut h = History::new(UndoPolicy::Limited(2));
+        h.record(0);
+        h.record(1);
+        h.record(2);
+        assert_eq!(h.undo(3), Ok(2));
+        assert_eq!(h.undo(2), Ok(1));
+        assert_eq!(h.undo(1), Err(1));
+    }
+
+    #[test]
+    fn disabled_never_undoes() {
+        let mut h = History::new(UndoPolicy::Disabled);
+        h.record(0);
+        assert!(!h.can_undo());
+        assert_eq!(h.undo(1), Err(1));
+    }
+}
@@ -0,0 +1,16 @@
+//! Headless threes engine: create a game with [`state::State`], list its legal moves, apply a
+//! [`model::direction::Direction`] and query the score, next tile and game over status.
+
+pub mod ai;
+pub mod history;
+pub mod math;
+pub mod model;
+pub mod notation;
+pub mod replay;
+pub mod rng;
+pub mod rules;
+pub mod save;
+pub mod scores;
+pub mod simulate;
+pub mod state;
+mod utils;
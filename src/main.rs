@@ -1,12 +1,283 @@
-use anyhow::Result;
+use std::path::PathBuf;
 
+use anyhow::{bail, Context, Result};
+use clap::{Parser, Subcommand, ValueEnum};
+use config::Config;
+use threes::{
+    ai::{
+        expectimax::Expectimax,
+        strategy::{self, Bot, Random, Strategy},
+    },
+    history::UndoPolicy,
+    model::size::Size,
+    rules::RuleSet,
+    scores::HighScores,
+    simulate::simulate,
+    state::State,
+};
+
+mod config;
 mod game;
-mod math;
-mod model;
-mod state;
 mod ui;
-mod utils;
+mod viewer;
+
+use ui::{
+    palette::ColorDepth,
+    theme::{Markers, Themes},
+};
+
+/// A clone of the game threes, playable from the terminal
+#[derive(Parser)]
+#[command(version, args_conflicts_with_subcommands = true)]
+struct Args {
+    #[command(subcommand)]
+    command: Option<Command>,
+    // playing is the default, the play arguments are accepted without the subcommand
+    #[command(flatten)]
+    play: PlayArgs,
+}
+
+#[derive(Subcommand)]
+enum Command {
+    /// Play in the terminal, the default
+    Play(PlayArgs),
+    /// Step through a recorded game
+    Replay(ReplayArgs),
+    /// Play games without a terminal and report statistics on them
+    Simulate(SimulateArgs),
+    /// Rate every move from a position
+    Solve(SolveArgs),
+    /// Print the high scores
+    Scores(ScoresArgs),
+}
+
+#[derive(clap::Args)]
+struct PlayArgs {
+    /// Start a new game with this seed instead of resuming the last one, the same seed and moves
+    /// always yield the same game
+    #[arg(long)]
+    seed: Option<u64>,
+    /// Start a new game on a board of this size, as <COLUMNS>x<ROWS>, instead of resuming the
+    /// last one, 4x4 or as configured by default
+    #[arg(long)]
+    size: Option<Size>,
+    /// Start a new game with these rules, threes, threes-random or 2048, instead of resuming the
+    /// last one, threes by default
+    #[arg(long)]
+    rules: Option<RuleSet>,
+    /// Number of moves that can be undone in new games, unlimited or as configured by default
+    #[arg(long, value_name = "MOVES")]
+    undo_depth: Option<usize>,
+    /// Disable undo and redo in new games for an honest run
+    #[arg(long, conflicts_with = "undo_depth")]
+    no_undo: bool,
+    /// Number of moves the autoplay and hints look ahead
+    #[arg(long, value_name = "MOVES", default_value_t = 2)]
+    ai_depth: u32,
+    /// Frames per second moves are animated at, 60 or as configured by default
+    #[arg(long, value_parser = clap::value_parser!(u32).range(1..))]
+    fps: Option<u32>,
+    /// Draw moves at once instead of animating them
+    #[arg(long)]
+    reduced_motion: bool,
+    /// Colours to play in, classic, dark, high-contrast or one of the themes directory, classic
+    /// or as configured by default
+    #[arg(long, value_name = "NAME")]
+    theme: Option<String>,
+    /// Colours the terminal can show, detected from its environment by default
+    #[arg(long, value_enum, value_name = "DEPTH")]
+    colors: Option<ColorDepth>,
+    /// Tell tiles of 1, of 2 and higher ones apart by glyphs or borders as well as by their
+    /// colours, as the theme does by default
+    #[arg(long, value_enum)]
+    markers: Option<Markers>,
+}
+
+#[derive(clap::Args)]
+struct ReplayArgs {
+    /// Recorded game, games are recorded in the replays data directory
+    file: PathBuf,
+}
+
+#[derive(clap::Args)]
+struct SolveArgs {
+    /// Position in notation, e.g. 3,1,-,-/-,2,-,-/-,-,-,-/-,-,-,6 2 for a grid followed by the
+    /// next tile
+    #[arg(required = true, num_args = 1..)]
+    position: Vec<String>,
+    /// Number of moves looked ahead
+    #[arg(long, value_name = "MOVES", default_value_t = 3)]
+    depth: u32,
+    /// Rules the position is played with, threes, threes-random or 2048
+    #[arg(long, default_value_t = RuleSet::default())]
+    rules: RuleSet,
+}
+
+#[derive(clap::Args)]
+struct ScoresArgs {
+    /// Rules whose high scores are printed
+    #[arg(long, default_value_t = RuleSet::default())]
+    rules: RuleSet,
+}
+
+#[derive(clap::Args)]
+struct SimulateArgs {
+    /// Number of games to play
+    #[arg(long, default_value_t = 100)]
+    games: usize,
+    /// How moves are chosen
+    #[arg(long, value_enum, default_value_t = StrategyKind::Expectimax)]
+    strategy: StrategyKind,
+    /// Command of the external bot, sent positions in notation on its stdin and answering with
+    /// U, D, L or R on its stdout
+    #[arg(long, value_name = "COMMAND", required_if_eq("strategy", "bot"))]
+    bot: Option<String>,
+    /// Number of moves the expectimax strategy looks ahead
+    #[arg(long, value_name = "MOVES", default_value_t = 2)]
+    depth: u32,
+    /// Seed of the first game, the following games use the next seeds
+    #[arg(long, default_value_t = 0)]
+    seed: u64,
+    /// Size of the boards, as <COLUMNS>x<ROWS>
+    #[arg(long, default_value_t = Size::default())]
+    size: Size,
+    /// Rules the games are played with, threes, threes-random or 2048
+    #[arg(long, default_value_t = RuleSet::default())]
+    rules: RuleSet,
+    #[arg(long, value_enum, default_value_t = Format::Table)]
+    format: Format,
+}
+
+#[derive(Clone, Copy, ValueEnum)]
+enum StrategyKind {
+    Random,
+    Greedy,
+    Expectimax,
+    Bot,
+}
+
+#[derive(Clone, Copy, ValueEnum)]
+enum Format {
+    Table,
+    Json,
+}
+
+impl SimulateArgs {
+    fn strategy(&self) -> Result<Box<dyn Strategy>> {
+        Ok(match self.strategy {
+            StrategyKind::Random => Box::new(Random::new(self.seed)),
+            StrategyKind::Greedy => Box::new(strategy::greedy()),
+            StrategyKind::Expectimax => Box::new(Expectimax::new(self.depth)),
+            StrategyKind::Bot => Box::new(Bot::spawn(self.bot.as_deref().unwrap_or_default())?),
+        })
+    }
+
+    fn run(&self) -> Result<()> {
+        let summary = simulate(
+            self.strategy()?.as_mut(),
+            self.games,
+            self.seed,
+            self.size,
+            self.rules,
+        )?;
+        match self.format {
+            Format::Table => print!("{}", summary),
+            Format::Json => println!("{}", serde_json::to_string_pretty(&summary)?),
+        }
+        Ok(())
+    }
+}
+
+impl PlayArgs {
+    // none if left to the config
+    fn undo_policy(&self) -> Option<UndoPolicy> {
+        match (self.no_undo, self.undo_depth) {
+            (true, _) | (_, Some(0)) => Some(UndoPolicy::Disabled),
+            (false, Some(depth)) => Some(UndoPolicy::Limited(depth)),
+            (false, None) => None,
+        }
+    }
+
+    // the command line overrides the config
+    fn run(&self) -> Result<()> {
+        let config = Config::load()?;
+        game::Game::run(game::Options {
+            seed: self.seed,
+            size: self.size,
+            default_size: config.size,
+            rules: self.rules,
+            undo_policy: self.undo_policy().unwrap_or(config.undo_policy),
+            ai_depth: self.ai_depth,
+            fps: self.fps.unwrap_or(config.fps),
+            animation: config.animation,
+            reduced_motion: self.reduced_motion || config.reduced_motion,
+            themes: themes(self.theme.as_deref(), self.colors, self.markers, &config)?,
+            keys: config.keys,
+        })
+    }
+}
+
+impl SolveArgs {
+    // every legal move with its expected value, best first
+    fn run(&self) -> Result<()> {
+        let position = self.position.join(" ");
+        let state = State::from_notation(&position, 0, self.rules)?;
+        let mut moves = Expectimax::new(self.depth).evaluate(&state);
+        if moves.is_empty() {
+            bail!("no move is legal from {}", state.notation());
+        }
+        moves.sort_by(|(_, a), (_, b)| b.total_cmp(a));
+        for (dir, value) in moves {
+            println!("{} {:.1}", dir, value);
+        }
+        Ok(())
+    }
+}
+
+impl ScoresArgs {
+    fn run(&self) -> Result<()> {
+        let scores = HighScores::read(&HighScores::default_path(self.rules)?)?;
+        if scores.entries().is_empty() {
+            println!("no {} game finished yet", self.rules);
+        } else {
+            print!("{}", scores);
+        }
+        Ok(())
+    }
+}
+
+// the theme, colour depth and markers given on the command line, else the config's
+fn themes(
+    name: Option<&str>,
+    depth: Option<ColorDepth>,
+    markers: Option<Markers>,
+    config: &Config,
+) -> Result<Themes> {
+    let depth = depth.or(config.colors).unwrap_or_else(ColorDepth::detect);
+    let mut themes = Themes::load(Themes::default_dir().as_deref())?.with_depth(depth);
+    if let Some(markers) = markers.or(config.markers) {
+        themes = themes.with_markers(markers);
+    }
+    match name {
+        Some(name) => themes.select(name),
+        None => themes
+            .select(&config.theme)
+            .context("invalid value for `theme` in the config"),
+    }
+}
 
 fn main() -> Result<()> {
-    game::Game::run()
+    let args = Args::parse();
+    match &args.command {
+        None => args.play.run(),
+        Some(Command::Play(play_args)) => play_args.run(),
+        Some(Command::Replay(replay_args)) => {
+            let config = Config::load()?;
+            let themes = themes(None, None, None, &config)?;
+            viewer::Viewer::run(&replay_args.file, config.keys, themes)
+        }
+        Some(Command::Simulate(simulate_args)) => simulate_args.run(),
+        Some(Command::Solve(solve_args)) => solve_args.run(),
+        Some(Command::Scores(scores_args)) => scores_args.run(),
+    }
 }
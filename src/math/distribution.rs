@@ -1,7 +1,8 @@
 use rand::{distributions::OpenClosed01, Rng};
+use serde::{Deserialize, Serialize};
 
 // geometric
-#[derive(Debug, Copy, Clone)]
+#[derive(Debug, Copy, Clone, Serialize, Deserialize)]
 pub struct Distribution {
     p: f64,
 }
@@ -12,6 +13,15 @@ impl Distribution {
         Distribution { p }
     }
 
+    // of sampling k, k >= 1
+    pub fn probability(&self, k: u32) -> f64 {
+        if k == 0 {
+            0.0
+        } else {
+            (1.0 - self.p).powi(k as i32 - 1) * self.p
+        }
+    }
+
     pub fn sample<R: Rng + ?Sized>(&self, random: &mut R) -> u32 {
         let x: f64 = random.sample(OpenClosed01);
         x.log(1.0 - self.p).ceil() as u32
@@ -20,16 +30,26 @@ impl Distribution {
 
 #[cfg(test)]
 mod tests {
-    use rand::rngs::OsRng;
+    use crate::rng;
 
     use super::*;
 
     #[test]
     fn sample_test() -> () {
         let d = Distribution::new(0.5);
-        let mut r = OsRng;
+        let mut r = rng::from_seed(42);
         let res = d.sample(&mut r);
         // true 99.9% of the time
         assert!(res < 10)
     }
+
+    #[test]
+    fn probability_is_geometric() {
+        let d = Distribution::new(0.5);
+        assert_eq!(d.probability(0), 0.0);
+        assert_eq!(d.probability(1), 0.5);
+        assert_eq!(d.probability(3), 0.125);
+        let total: f64 = (1..64).map(|k| d.probability(k)).sum();
+        assert!((total - 1.0).abs() < 1e-9);
+    }
 }
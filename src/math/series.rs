@@ -1,4 +1,6 @@
-#[derive(Debug, Copy, Clone)]
+use serde::{Deserialize, Serialize};
+
+#[derive(Debug, Copy, Clone, Serialize, Deserialize)]
 pub struct Series {
     u_0: u32,
     u_1: u32,
@@ -7,7 +9,7 @@ pub struct Series {
 }
 
 impl Series {
-    pub fn new(u_0: u32, u_1: u32, q: u32) -> Series {
+    pub const fn new(u_0: u32, u_1: u32, q: u32) -> Series {
         Series {
             u_0,
             u_1,
@@ -0,0 +1,433 @@
+use anyhow::{bail, Result};
+use nalgebra::DMatrix;
+use once_cell::sync::Lazy;
+use rand::Rng;
+
+use crate::{
+    rng,
+    rules::{insertion::Insertion, RuleSet, Rules},
+};
+
+use super::{dimension::Dimension, direction::Direction, grid::Grid, size::Size};
+
+// number of rows of 4 nibbles
+const ROWS: usize = 1 << 16;
+const ROW_MASK: u64 = 0xFFFF;
+// the highest rank a nibble holds
+const MAX_RANK: usize = 15;
+
+// how every row of 4 ranks shifts towards its first cell under some rules, so that shifting a
+// board is a few lookups
+pub struct Tables {
+    // None for rows which combine two tiles of the highest rank, past what a nibble holds
+    shifted: Box<[Option<u16>]>,
+    combined: Box<[bool]>,
+    values: [u32; MAX_RANK + 1],
+    scores: [u64; MAX_RANK + 1],
+    insertion: Insertion,
+}
+
+static THREES: Lazy<Tables> = Lazy::new(|| Tables::new(RuleSet::Threes.rules()));
+static THREES_RANDOM: Lazy<Tables> = Lazy::new(|| Tables::new(RuleSet::ThreesRandom.rules()));
+static TWENTY_FORTY_EIGHT: Lazy<Tables> =
+    Lazy::new(|| Tables::new(RuleSet::TwentyFortyEight.rules()));
+
+impl Tables {
+    pub fn new(rules: &dyn Rules) -> Tables {
+        let values = std::array::from_fn(|rank| rules.value(rank as u32));
+        let scores = values.map(|value| rules.tile_score(value));
+        let mut shifted = vec![None; ROWS];
+        let mut combined = vec![false; ROWS];
+        for row in 0..ROWS {
+            let line: Vec<u32> = (0..4).map(|j| values[(row >> (4 * j)) & 0xF]).collect();
+            let (new_line, _, comb) = rules.shift_line(&line);
+            let ranks: Vec<u32> = new_line.iter().map(|v| rules.rank(*v)).collect();
+            combined[row] = comb;
+            if ranks.iter().any(|r| *r as usize > MAX_RANK) {
+                continue;
+            }
+            shifted[row] = Some(
+                ranks
+                    .iter()
+                    .enumerate()
+                    .fold(0, |acc, (j, r)| acc | (*r as u16) << (4 * j)),
+            );
+        }
+        Tables {
+            shifted: shifted.into_boxed_slice(),
+            combined: combined.into_boxed_slice(),
+            values,
+            scores,
+            insertion: rules.insertion(),
+        }
+    }
+
+    // built once per rule set, on first use
+    pub fn of(rules: RuleSet) -> &'static Tables {
+        match rules {
+            RuleSet::Threes => &THREES,
+            RuleSet::ThreesRandom => &THREES_RANDOM,
+            RuleSet::TwentyFortyEight => &TWENTY_FORTY_EIGHT,
+        }
+    }
+
+    fn rank(&self, value: u32) -> Option<u64> {
+        self.values
+            .iter()
+            .position(|v| *v == value)
+            .map(|rank| rank as u64)
+    }
+}
+
+// a 4x4 grid packed as the ranks of its tiles, a nibble per cell row by row from the lowest bits
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Default)]
+pub struct Bitboard(pub u64);
+
+// what shifting a bitboard resulted in, as Grid::shift does
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Shifted {
+    pub board: Bitboard,
+    pub spawned: Option<(usize, usize)>,
+    pub game_over: bool,
+}
+
+impl Bitboard {
+    pub fn from_grid(tables: &Tables, grid: &Grid) -> Result<Bitboard> {
+        if grid.size() != Size::default() {
+            bail!("expected a 4x4 grid, found {}", grid.size());
+        }
+        let mut board = 0;
+        for i in 0..4 {
+            for j in 0..4 {
+                let value = grid.matrix[(i, j)];
+                let Some(rank) = tables.rank(value) else {
+                    bail!("{} doesn't fit in a bitboard", value);
+                };
+                board |= rank << Self::offset(i, j);
+            }
+        }
+        Ok(Bitboard(board))
+    }
+
+    pub fn to_grid(self, tables: &Tables) -> Grid {
+        Grid::new(DMatrix::from_fn(4, 4, |i, j| {
+            tables.values[self.rank(i, j) as usize]
+        }))
+    }
+
+    fn offset(row: usize, col: usize) -> u64 {
+        4 * (4 * row + col) as u64
+    }
+
+    pub fn rank(self, row: usize, col: usize) -> u32 {
+        ((self.0 >> Self::offset(row, col)) & 0xF) as u32
+    }
+
+    pub fn empty_cells(self) -> usize {
+        (0..16).filter(|i| (self.0 >> (4 * i)) & 0xF == 0).count()
+    }
+
+    pub fn score(self, tables: &Tables) -> u64 {
+        (0..16)
+            .map(|i| tables.scores[((self.0 >> (4 * i)) & 0xF) as usize])
+            .sum()
+    }
+
+    // the board once every line shifted, None if a line combined past the highest rank, with a bit
+    // per line which moved and per line which combined
+    fn slide(self, tables: &Tables, dir: Direction) -> (Option<Bitboard>, u8, u8) {
+        let transposed = dir.associated_dimension() == Dimension::Col;
+        let board = if transposed {
+            transpose(self.0)
+        } else {
+            self.0
+        };
+        let reverse = dir.reverse_needed();
+        let mut res = Some(0);
+        let mut moved = 0;
+        let mut combined = 0;
+        for i in 0..4 {
+            let row = ((board >> (16 * i)) & ROW_MASK) as u16;
+            let line = if reverse { reverse_row(row) } else { row };
+            let shifted = tables.shifted[line as usize];
+            if shifted != Some(line) {
+                moved |= 1 << i;
+                if tables.combined[line as usize] {
+                    combined |= 1 << i;
+                }
+            }
+            res = res.zip(shifted).map(|(res, shifted)| {
+                let new_row = if reverse {
+                    reverse_row(shifted)
+                } else {
+                    shifted
+                };
+                res | (new_row as u64) << (16 * i)
+            });
+        }
+        let res = res.map(|res| Bitboard(if transposed { transpose(res) } else { res }));
+        (res, moved, combined)
+    }
+
+    pub fn can_shift(self, tables: &Tables, dir: Direction) -> bool {
+        self.slide(tables, dir).1 != 0
+    }
+
+    pub fn legal_moves(self, tables: &Tables) -> Vec<Direction> {
+        Direction::ALL
+            .into_iter()
+            .filter(|d| self.can_shift(tables, *d))
+            .collect()
+    }
+
+    pub fn game_over(self, tables: &Tables) -> bool {
+        self.empty_cells() == 0 && !Direction::ALL.iter().any(|d| self.can_shift(tables, *d))
+    }
+
+    // None if the shifted board or the next tile doesn't fit in nibbles, Grid::shift then tells
+    // what happens
+    pub fn shift<R: Rng + ?Sized>(
+        self,
+        tables: &Tables,
+        r: &mut R,
+        dir: Direction,
+        next_tile: u32,
+    ) -> Option<Shifted> {
+        let (board, moved, combined) = self.slide(tables, dir);
+        let mut board = board?;
+        let mut spawned = None;
+        if moved != 0 {
+            let (cells, n) = board.spawn_cells(tables.insertion, dir, moved, combined);
+            if n > 0 {
+                let cell = if n == 1 {
+                    cells[0]
+                } else {
+                    cells[rng::index(r, n)]
+                };
+                let rank = tables.rank(next_tile)?;
+                board.0 |= rank << Self::offset(cell.0, cell.1);
+                spawned = Some(cell);
+            }
+        }
+        Some(Shifted {
+            board,
+            spawned,
+            game_over: board.game_over(tables),
+        })
+    }
+
+    // as Insertion::cells, in the same order, without allocating
+    fn spawn_cells(
+        self,
+        insertion: Insertion,
+        dir: Direction,
+        moved: u8,
+        combined: u8,
+    ) -> ([(usize, usize); 16], usize) {
+        let trailing = |line: usize| match dir {
+            Direction::Up => (3, line),
+            Direction::Down => (0, line),
+            Direction::Left => (line, 3),
+            Direction::Right => (line, 0),
+        };
+        let mut cells = [(0, 0); 16];
+        let mut n = 0;
+        let mut push = |cell: (usize, usize)| {
+            if self.rank(cell.0, cell.1) == 0 {
+                cells[n] = cell;
+                n += 1;
+            }
+        };
+        match insertion {
+            Insertion::MovedLine => (0..4)
+                .filter(|i| moved & (1 << i) != 0)
+                .for_each(|i| push(trailing(i))),
+            Insertion::FirstCombined if combined != 0 => {
+                push(trailing(combined.trailing_zeros() as usize))
+            }
+            Insertion::FirstCombined => (0..4).for_each(|i| push(trailing(i))),
+            Insertion::AnyFreeCell => (0..16).for_each(|i| push((i / 4, i % 4))),
+        }
+        (cells, n)
+    }
+}
+
+// swaps rows and columns, a nibble moving at most 3 rows and 3 columns away
+fn transpose(x: u64) -> u64 {
+    let a1 = x & 0xF0F0_0F0F_F0F0_0F0F;
+    let a2 = x & 0x0000_F0F0_0000_F0F0;
+    let a3 = x & 0x0F0F_0000_0F0F_0000;
+    let a = a1 | (a2 << 12) | (a3 >> 12);
+    let b1 = a & 0xFF00_FF00_00FF_00FF;
+    let b2 = a & 0x00FF_00FF_0000_0000;
+    let b3 = a & 0x0000_0000_FF00_FF00;
+    b1 | (b2 >> 24) | (b3 << 24)
+}
+
+fn reverse_row(row: u16) -> u16 {
+    (row >> 12) | ((row >> 4) & 0x00F0) | ((row << 4) & 0x0F00) | (row << 12)
+}
+
+#[cfg(test)]
+mod tests {
+    use nalgebra::Matrix4;
+    use rand::seq::SliceRandom;
+
+    use crate::rng;
+
+    use super::*;
+
+    #[test]
+    fn grids_round_trip() {
+        let tables = Tables::of(RuleSet::Threes);
+        let g = Grid::from(Matrix4::new(
+            0, 1, 2, 3, 6, 12, 24, 48, 96, 192, 384, 768, 1536, 3072, 6144, 12288,
+        ));
+        let board = Bitboard::from_grid(tables, &g).unwrap();
+        assert_eq!(board.rank(0, 1), 1);
+        assert_eq!(board.rank(3, 3), 15);
+        assert_eq!(board.to_grid(tables), g);
+    }
+
+    #[test]
+    fn from_grid_rejects_what_does_not_fit() {
+        let tables = Tables::of(RuleSet::Threes);
+        assert!(Bitboard::from_grid(tables, &Grid::empty(Size { cols: 5, rows: 4 })).is_err());
+        let mut m = Matrix4::repeat(0);
+        m[(0, 0)] = 24576;
+        assert!(Bitboard::from_grid(tables, &Grid::from(m)).is_err());
+        m[(0, 0)] = 5;
+        assert!(Bitboard::from_grid(tables, &Grid::from(m)).is_err());
+    }
+
+    #[test]
+    fn transpose_swaps_rows_and_columns() {
+        let tables = Tables::of(RuleSet::Threes);
+        let g = Grid::from(Matrix4::new(
+            1, 2, 3, 6, 0, 0, 0, 12, 0, 0, 24, 0, 48, 0, 0, 96,
+        ));
+        let board = Bitboard::from_grid(tables, &g).unwrap();
+        let transposed = Bitboard(transpose(board.0)).to_grid(tables);
+        assert_eq!(transposed.matrix, g.matrix.transpose());
+        assert_eq!(transpose(transpose(board.0)), board.0);
+    }
+
+    #[test]
+    fn reverse_row_reverses_nibbles() {
+        assert_eq!(reverse_row(0x4321), 0x1234);
+        assert_eq!(reverse_row(0x00F0), 0x0F00);
+    }
+
+    // plays random games with both representations, checking they agree after every move
+    fn assert_equivalent(rules: RuleSet, games: u64) {
+        let tables = Tables::of(rules);
+        for seed in 0..games {
+            let mut r = rng::from_seed(seed);
+            let mut grid = rules.rules().initial_grid(&mut r, Size::default());
+            let mut board = Bitboard::from_grid(tables, &grid).unwrap();
+            let mut grid_rng = rng::from_seed(seed);
+            let mut board_rng = rng::from_seed(seed);
+            loop {
+                assert_eq!(board.legal_moves(tables), grid.legal_moves(rules.rules()));
+                let Some(dir) = grid.legal_moves(rules.rules()).choose(&mut r).copied() else {
+                    break;
+                };
+                let tile = rules.rules().first_tile(&mut r).current();
+                let outcome = grid.shift(rules.rules(), &mut grid_rng, dir, tile);
+                let shifted = board.shift(tables, &mut board_rng, dir, tile).unwrap();
+                assert_eq!(shifted.board.to_grid(tables), outcome.grid);
+                assert_eq!(shifted.spawned, outcome.spawned.map(|s| s.at));
+                assert_eq!(shifted.game_over, outcome.game_over);
+                assert_eq!(
+                    shifted.board.score(tables),
+                    outcome.grid.score(rules.rules())
+                );
+                grid = outcome.grid;
+                board = shifted.board;
+            }
+            assert!(board.game_over(tables));
+        }
+    }
+
+    #[test]
+    fn shift_matches_grid_for_threes() {
+        assert_equivalent(RuleSet::Threes, 20);
+    }
+
+    #[test]
+    fn shift_matches_grid_for_threes_random() {
+        assert_equivalent(RuleSet::ThreesRandom, 20);
+    }
+
+    #[test]
+    fn shift_matches_grid_for_2048() {
+        assert_equivalent(RuleSet::TwentyFortyEight, 5);
+    }
+
+    #[test]
+    fn rows_which_would_overflow_do_not_fit() {
+        let tables = Tables::of(RuleSet::TwentyFortyEight);
+        assert_eq!(tables.shifted[0xFF], None);
+        assert!(tables.combined[0xFF]);
+    }
+
+    // every line of empty cells, the lowest and the highest ranks, across the first row and the
+    // first column
+    fn assert_equivalent_at_the_edges(rules: RuleSet) {
+        let tables = Tables::of(rules);
+        let ranks = [0, 1, 2, 3, 13, 14, 15];
+        let tile = rules.rules().value(1);
+        for n in 0..ranks.len().pow(4) {
+            let line: Vec<u32> = (0..4)
+                .map(|j| {
+                    rules
+                        .rules()
+                        .value(ranks[n / ranks.len().pow(j) % ranks.len()])
+                })
+                .collect();
+            let mut m = Matrix4::repeat(0);
+            for j in 0..4 {
+                m[(0, j)] = line[j];
+                m[(j, 0)] = line[j];
+            }
+            let grid = Grid::from(m);
+            let board = Bitboard::from_grid(tables, &grid).unwrap();
+            for dir in Direction::ALL {
+                assert_eq!(
+                    board.can_shift(tables, dir),
+                    grid.can_shift(rules.rules(), dir)
+                );
+                let outcome = grid.shift(rules.rules(), &mut rng::from_seed(0), dir, tile);
+                match board.shift(tables, &mut rng::from_seed(0), dir, tile) {
+                    Some(shifted) => {
+                        assert_eq!(shifted.board.to_grid(tables), outcome.grid, "{:?}", line);
+                        assert_eq!(shifted.spawned, outcome.spawned.map(|s| s.at));
+                        assert_eq!(shifted.game_over, outcome.game_over);
+                    }
+                    None => assert!(
+                        Bitboard::from_grid(tables, &outcome.grid).is_err(),
+                        "{:?}",
+                        line
+                    ),
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn shift_matches_grid_at_the_edge_ranks() {
+        assert_equivalent_at_the_edges(RuleSet::Threes);
+        assert_equivalent_at_the_edges(RuleSet::TwentyFortyEight);
+    }
+
+    #[test]
+    fn shift_rejects_a_next_tile_which_does_not_fit() {
+        let tables = Tables::of(RuleSet::Threes);
+        let mut m = Matrix4::repeat(0);
+        m[(0, 1)] = 3;
+        let board = Bitboard::from_grid(tables, &Grid::from(m)).unwrap();
+        let mut r = rng::from_seed(0);
+        assert_eq!(board.shift(tables, &mut r, Direction::Left, 24576), None);
+        assert!(board.shift(tables, &mut r, Direction::Left, 1).is_some());
+    }
+}
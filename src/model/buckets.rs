@@ -1,5 +1,7 @@
 use rand::{distributions::Uniform, Rng};
 
+use crate::rng;
+
 pub struct Buckets {
     storage: Vec<u32>,
     desired_size: usize,
@@ -9,6 +11,7 @@ pub struct Buckets {
 // e.g. I want to generate 4 elements but not more than one each
 impl Buckets {
     // unsafe for large generation
+    #[allow(clippy::match_single_binding)]
     pub fn new<R: Rng + ?Sized, I>(r: &mut R, base_values: I, desired_size: usize) -> Buckets
     where
         I: IntoIterator<Item = u32>,
@@ -16,17 +19,15 @@ impl Buckets {
         let (sum, size, mut elements) =
             base_values
                 .into_iter()
-                .fold((0, 0, Vec::<u32>::new()), |acc, e| match acc {
+                .fold((0, 0_u32, Vec::<u32>::new()), |acc, e| match acc {
                     (sum, size, mut vec) => {
                         vec.push(e);
                         (sum + e, size + 1, vec)
                     }
                 });
         let missing_elements = desired_size as u32 - sum;
-        let distribution = Uniform::new(0, size);
         for _i in 0..missing_elements {
-            let index = r.sample(distribution);
-            elements[index] = elements[index] + 1;
+            elements[rng::index(r, size as usize)] += 1;
         }
         Buckets {
             storage: elements,
@@ -37,16 +38,16 @@ impl Buckets {
     // should not consume self
     pub fn draw<R: Rng + ?Sized>(&self, r: &mut R) -> Vec<u32> {
         let mut res = Vec::with_capacity(self.desired_size);
-        let distribution = Uniform::new(0, self.storage.len());
+        let distribution = Uniform::new(0, self.storage.len() as u32);
         // clone to not mut self
         let mut st = self.storage.clone();
         for _i in 0..self.desired_size {
             loop {
                 let sampled_idx = r.sample(distribution);
-                let e = st[sampled_idx];
+                let e = st[sampled_idx as usize];
                 if e > 0 {
-                    res.push(sampled_idx as u32);
-                    st[sampled_idx] = e - 1;
+                    res.push(sampled_idx);
+                    st[sampled_idx as usize] = e - 1;
                     break;
                 }
             }
@@ -59,14 +60,14 @@ impl Buckets {
 mod tests {
     use std::cmp::Ordering;
 
-    use rand::rngs::OsRng;
+    use crate::rng;
 
     use super::*;
 
     #[test]
     // idempotent ignoring rng
     fn draw_should_be_idempotent() -> () {
-        let mut rng = OsRng;
+        let mut rng = rng::from_seed(42);
         let init = [4, 2, 2, 2];
         let desired_size = 16;
         let bs = Buckets::new(&mut rng, init, desired_size);
@@ -81,7 +82,7 @@ mod tests {
 
     #[test]
     fn draw_should_construct_vec_with_desired_size() -> () {
-        let mut rng = OsRng;
+        let mut rng = rng::from_seed(42);
         let init = [4, 2, 2, 2];
         let desired_size = 16;
         let bs = Buckets::new(&mut rng, init, desired_size);
@@ -91,7 +92,7 @@ mod tests {
 
     #[test]
     fn draw_should_construct_vec_between_0_and_len() -> () {
-        let mut rng = OsRng;
+        let mut rng = rng::from_seed(42);
         let init = [4, 2, 2, 2];
         let desired_size = 16;
         let bs = Buckets::new(&mut rng, init, desired_size);
@@ -101,7 +102,7 @@ mod tests {
 
     #[test]
     fn new_copies_array_argument() -> () {
-        let mut rng = OsRng;
+        let mut rng = rng::from_seed(42);
         let init = [4, 2, 2, 2];
         let desired_size = 16;
         Buckets::new(&mut rng, init, desired_size);
@@ -113,7 +114,7 @@ mod tests {
 
     #[test]
     fn new_generates_an_array_with_sum_desired_size() -> () {
-        let mut rng = OsRng;
+        let mut rng = rng::from_seed(42);
         let init = [4, 2, 2, 2];
         let desired_size = 16;
         let bs = Buckets::new(&mut rng, init, desired_size);
@@ -122,7 +123,7 @@ mod tests {
 
     #[test]
     fn new_has_same_length_as_init() -> () {
-        let mut rng = OsRng;
+        let mut rng = rng::from_seed(42);
         let init = vec![4, 2, 2, 2];
         let desired_size = 16;
         let bs = Buckets::new(&mut rng, init.clone(), desired_size);
@@ -131,7 +132,7 @@ mod tests {
 
     #[test]
     fn new_adds_to_init() -> () {
-        let mut rng = OsRng;
+        let mut rng = rng::from_seed(42);
         let init = [4, 2, 2, 2];
         let desired_size = 16;
         let bs = Buckets::new(&mut rng, init, desired_size);
@@ -140,7 +141,7 @@ mod tests {
 
     #[test]
     fn new_desired_size_is_arg() -> () {
-        let mut rng = OsRng;
+        let mut rng = rng::from_seed(42);
         let init = [4, 2, 2, 2];
         let desired_size = 16;
         let bs = Buckets::new(&mut rng, init, desired_size);
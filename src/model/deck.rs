@@ -0,0 +1,106 @@
+use rand::{distributions::Uniform, Rng};
+use serde::{Deserialize, Serialize};
+
+// the 1s, 2s and 3s left in a shuffled deck of four of each, drawing a random card left is the
+// same as drawing the top card of the shuffled deck
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Deck {
+    cards: [u32; 3],
+}
+
+impl Default for Deck {
+    fn default() -> Deck {
+        Deck {
+            cards: [Self::COPIES; 3],
+        }
+    }
+}
+
+impl Deck {
+    // of each card
+    pub const COPIES: u32 = 4;
+
+    pub fn remaining(&self) -> u32 {
+        self.cards.iter().sum()
+    }
+
+    // a new deck is shuffled once the last card is drawn
+    pub fn draw<R: Rng + ?Sized>(&mut self, r: &mut R) -> u32 {
+        if self.remaining() == 0 {
+            *self = Deck::default();
+        }
+        let mut i = r.sample(Uniform::new(0, self.remaining()));
+        let card = self
+            .cards
+            .iter()
+            .position(|c| {
+                if i < *c {
+                    true
+                } else {
+                    i -= c;
+                    false
+                }
+            })
+            .unwrap_or_default();
+        self.cards[card] -= 1;
+        card as u32 + 1
+    }
+
+    // every card draw can return with the deck left and its probability
+    pub fn outcomes(&self) -> Vec<(u32, Deck, f64)> {
+        let deck = if self.remaining() == 0 {
+            Deck::default()
+        } else {
+            *self
+        };
+        let total = deck.remaining() as f64;
+        (0..deck.cards.len())
+            .filter(|i| deck.cards[*i] > 0)
+            .map(|i| {
+                let mut left = deck;
+                left.cards[i] -= 1;
+                (i as u32 + 1, left, deck.cards[i] as f64 / total)
+            })
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::rng;
+
+    use super::*;
+
+    #[test]
+    fn a_deck_holds_four_of_each_card() {
+        let mut r = rng::from_seed(42);
+        let mut deck = Deck::default();
+        let mut cards: Vec<u32> = (0..12).map(|_| deck.draw(&mut r)).collect();
+        cards.sort();
+        assert_eq!(cards, vec![1, 1, 1, 1, 2, 2, 2, 2, 3, 3, 3, 3]);
+        assert_eq!(deck.remaining(), 0);
+    }
+
+    #[test]
+    fn an_empty_deck_is_reshuffled() {
+        let mut r = rng::from_seed(42);
+        let mut deck = Deck::default();
+        for _ in 0..12 {
+            deck.draw(&mut r);
+        }
+        deck.draw(&mut r);
+        assert_eq!(deck.remaining(), 11);
+    }
+
+    #[test]
+    fn outcomes_follow_the_cards_left() {
+        let deck = Deck { cards: [0, 1, 3] };
+        let outcomes = deck.outcomes();
+        assert_eq!(outcomes.len(), 2);
+        assert_eq!(outcomes[0].0, 2);
+        assert_eq!(outcomes[0].2, 0.25);
+        assert_eq!(outcomes[1].1, Deck { cards: [0, 1, 2] });
+        let empty = Deck { cards: [0, 0, 0] };
+        assert_eq!(empty.outcomes().len(), 3);
+    }
+}
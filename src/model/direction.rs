@@ -1,8 +1,8 @@
-use crossterm::event::KeyCode;
+use serde::{Deserialize, Serialize};
 
 use super::dimension::Dimension;
 
-#[derive(Debug, PartialEq, Eq, Copy, Clone, Hash)]
+#[derive(Debug, PartialEq, Eq, Copy, Clone, Hash, Serialize, Deserialize)]
 pub enum Direction {
     Up,
     Down,
@@ -11,15 +11,12 @@ pub enum Direction {
 }
 
 impl Direction {
-    pub fn from_key_code(key_code: KeyCode) -> Option<Direction> {
-        match key_code {
-            KeyCode::Up | KeyCode::Char('w') => Some(Direction::Up),
-            KeyCode::Down | KeyCode::Char('s') => Some(Direction::Down),
-            KeyCode::Left | KeyCode::Char('a') => Some(Direction::Left),
-            KeyCode::Right | KeyCode::Char('d') => Some(Direction::Right),
-            _ => None,
-        }
-    }
+    pub const ALL: [Direction; 4] = [
+        Direction::Up,
+        Direction::Down,
+        Direction::Left,
+        Direction::Right,
+    ];
 
     pub fn reverse_needed(self) -> bool {
         self == Direction::Down || self == Direction::Right
@@ -32,12 +29,11 @@ impl Direction {
         }
     }
 
-    pub fn index(self) -> usize {
+    // the line at the trailing edge of a shift, out of `lines` lines
+    pub fn index(self, lines: usize) -> usize {
         match self {
-            Direction::Up => 3,
-            Direction::Down => 0,
-            Direction::Left => 3,
-            Direction::Right => 0,
+            Direction::Up | Direction::Left => lines - 1,
+            Direction::Down | Direction::Right => 0,
         }
     }
 }
@@ -1,133 +1,304 @@
-use nalgebra::{Matrix4, RowVector4, SMatrix, Vector4};
-use rand::{distributions::Uniform, Rng};
+use nalgebra::{DMatrix, DVector, RowDVector, SMatrix};
+use rand::Rng;
+use serde::{Deserialize, Serialize};
 
-use crate::utils::matrix_any::MatrixAny;
+use crate::{rng, rules::Rules, utils::matrix_any::MatrixAny};
 
-use super::{buckets::Buckets, dimension::Dimension, direction::Direction};
+use super::{
+    buckets::Buckets,
+    dimension::Dimension,
+    direction::Direction,
+    outcome::{Merge, Motion, MoveOutcome, Spawn},
+    size::Size,
+};
 
-#[derive(Clone, Copy)]
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
 pub struct Grid {
-    pub matrix: SMatrix<u32, 4, 4>,
+    pub matrix: DMatrix<u32>,
+}
+
+// a grid whose lines were shifted, before the next tile spawns
+#[derive(Debug, Clone, PartialEq)]
+pub struct Slide {
+    pub grid: Grid,
+    pub direction: Direction,
+    // indices of the lines which moved, and of those which combined, in order
+    pub moved: Vec<usize>,
+    pub combined: Vec<usize>,
+}
+
+impl Slide {
+    // the cell a line ends on, where tiles enter it
+    pub fn trailing_cell(&self, line: usize) -> (usize, usize) {
+        let inverse_dim = self.direction.associated_dimension().inverse();
+        let edge = self.direction.index(self.grid.lines(inverse_dim));
+        match inverse_dim {
+            Dimension::Row => (edge, line),
+            Dimension::Col => (line, edge),
+        }
+    }
+
+    // the empty cells of the line at the trailing edge
+    pub fn empty_edge_cells(&self) -> Vec<(usize, usize)> {
+        let dim = self.direction.associated_dimension();
+        (0..self.grid.lines(dim))
+            .map(|i| self.trailing_cell(i))
+            .filter(|c| self.grid.matrix[*c] == 0)
+            .collect()
+    }
 }
 
 impl Grid {
+    pub fn new(matrix: DMatrix<u32>) -> Grid {
+        Grid { matrix }
+    }
+
+    pub fn empty(size: Size) -> Grid {
+        Grid {
+            matrix: DMatrix::zeros(size.rows, size.cols),
+        }
+    }
 
-    pub fn rand<R: Rng + ?Sized, I>(r: &mut R, base_values: I) -> Grid
+    pub fn rand<R: Rng + ?Sized, I>(r: &mut R, base_values: I, size: Size) -> Grid
     where
         I: IntoIterator<Item = u32>,
     {
-        let grid_size = 16;
-        let buckets = Buckets::new(r, base_values, grid_size);
+        let buckets = Buckets::new(r, base_values, size.cells());
         let elements = buckets.draw(r);
-        let m = Matrix4::from_iterator(elements);
+        let m = DMatrix::from_iterator(size.rows, size.cols, elements);
         Grid { matrix: m }
     }
 
+    pub fn size(&self) -> Size {
+        Size {
+            cols: self.matrix.ncols(),
+            rows: self.matrix.nrows(),
+        }
+    }
+
+    // number of lines along a dimension
+    fn lines(&self, dim: Dimension) -> usize {
+        if dim == Dimension::Col {
+            self.matrix.ncols()
+        } else {
+            self.matrix.nrows()
+        }
+    }
+
+    // the next tile spawns unless the shift is not legal or the rules leave it nowhere to go
     pub fn shift<R: Rng + ?Sized>(
-        mut self,
+        &self,
+        rules: &dyn Rules,
         r: &mut R,
         dir: Direction,
         next_tile: u32,
-    ) -> (Grid, bool, bool) {
+    ) -> MoveOutcome {
+        let slide = self.slide(rules, dir);
+        let cells = rules.spawn_cells(&slide);
+        let (motions, merges) = self.motions(rules, &slide);
+        let legal = !slide.moved.is_empty();
+        let mut outcome = MoveOutcome {
+            grid: slide.grid,
+            direction: dir,
+            motions,
+            merges,
+            spawned: None,
+            score_delta: 0,
+            game_over: false,
+        };
+        if legal && !cells.is_empty() {
+            let cell = if cells.len() == 1 {
+                cells[0]
+            } else {
+                cells[rng::index(r, cells.len())]
+            };
+            outcome.grid.matrix[cell] = next_tile;
+            outcome.spawned = Some(Spawn {
+                at: cell,
+                value: next_tile,
+            });
+        }
+        // known as soon as the grid is stuck rather than on the next attempted move
+        outcome.game_over = outcome.grid.game_over(rules);
+        outcome.score_delta = outcome.grid.score(rules).saturating_sub(self.score(rules));
+        outcome
+    }
+
+    // every grid shift can result in, with its probability, empty if the shift is not legal
+    pub fn shift_outcomes(
+        &self,
+        rules: &dyn Rules,
+        dir: Direction,
+        next_tile: u32,
+    ) -> Vec<(Grid, f64)> {
+        let slide = self.slide(rules, dir);
+        if slide.moved.is_empty() {
+            return Vec::new();
+        }
+        let cells = rules.spawn_cells(&slide);
+        if cells.is_empty() {
+            return vec![(slide.grid, 1.0)];
+        }
+        let p = 1.0 / cells.len() as f64;
+        cells
+            .into_iter()
+            .map(|cell| {
+                let mut g = slide.grid.clone();
+                g.matrix[cell] = next_tile;
+                (g, p)
+            })
+            .collect()
+    }
+
+    // shifts every line towards the direction, as the rules move them
+    pub fn slide(&self, rules: &dyn Rules, dir: Direction) -> Slide {
         let reverse_needed = dir.reverse_needed();
         let dim = dir.associated_dimension();
 
-        let mut next_tile_inserted = false;
-        let mut mutated = false;
+        let mut grid = self.clone();
+        let mut moved = Vec::new();
+        let mut combined = Vec::new();
 
-        let size = if dim == Dimension::Col {
-            self.matrix.ncols()
-        } else {
-            self.matrix.nrows()
-        };
-        for i in 0..size {
-            if let Some(mut elements) = Self::get_line(self.matrix, i, dim) {
+        for i in 0..self.lines(dim) {
+            if let Some(mut elements) = Self::get_line(&self.matrix, i, dim) {
                 if reverse_needed {
                     elements.reverse()
                 }
-                let (mut new_line, muta, combined) =
-                    Self::shift_line(&elements, next_tile, next_tile_inserted);
-                if muta {
-                    mutated = true;
-                    if !next_tile_inserted && combined {
-                        next_tile_inserted = true;
+                let (mut new_line, _, comb) = rules.shift_line(&elements);
+                // empty cells sliding over each other move nothing
+                if new_line != elements {
+                    moved.push(i);
+                    if comb {
+                        combined.push(i);
                     }
                     if reverse_needed {
                         new_line.reverse()
                     }
-                    if dim == Dimension::Col {
-                        self.matrix
-                            .set_column(i, &Vector4::from_row_slice(&new_line));
-                    } else {
-                        self.matrix
-                            .set_row(i, &RowVector4::from_row_slice(&new_line));
-                    }
+                    grid.set_line(i, dim, &new_line);
                 }
             }
         }
+        Slide {
+            grid,
+            direction: dir,
+            moved,
+            combined,
+        }
+    }
 
-        if !next_tile_inserted && mutated {
-            let idx = dir.index();
-            let inverse_dim = dim.inverse();
-            if let Some(line_with_next_tile) =
-                Self::force_insert_next_tile(r, self.matrix, idx, inverse_dim, next_tile)
-            {
-                if inverse_dim == Dimension::Col {
-                    self.matrix
-                        .set_column(idx, &Vector4::from_row_slice(&line_with_next_tile));
-                } else {
-                    self.matrix
-                        .set_row(idx, &RowVector4::from_row_slice(&line_with_next_tile));
+    // where every tile of this grid went in the slide and which ones merged, as the rules moved
+    // them
+    fn motions(&self, rules: &dyn Rules, slide: &Slide) -> (Vec<Motion>, Vec<Merge>) {
+        let dir = slide.direction;
+        let dim = dir.associated_dimension();
+        // position 0 is the end of the line tiles move towards
+        let cell = |line: usize, pos: usize, len: usize| {
+            let along = if dir.reverse_needed() {
+                len - 1 - pos
+            } else {
+                pos
+            };
+            match dim {
+                Dimension::Col => (along, line),
+                Dimension::Row => (line, along),
+            }
+        };
+        let mut motions = Vec::new();
+        let mut merges = Vec::new();
+        for i in 0..self.lines(dim) {
+            let Some(mut line) = Self::get_line(&self.matrix, i, dim) else {
+                continue;
+            };
+            if dir.reverse_needed() {
+                line.reverse();
+            }
+            let len = line.len();
+            for (q, from) in rules.line_sources(&line).iter().enumerate() {
+                let to = cell(i, q, len);
+                for p in from {
+                    motions.push(Motion {
+                        from: cell(i, *p, len),
+                        to,
+                        value: line[*p],
+                    });
+                }
+                if let [a, b] = from[..] {
+                    if let Some(value) = rules.combine(line[a], line[b]) {
+                        merges.push(Merge { at: to, value });
+                    }
                 }
-                next_tile_inserted = true;
             }
         }
-        if next_tile_inserted {
-            (self, next_tile_inserted, false)
-        } else {
-            let game_over = self.game_over();
-            (self, next_tile_inserted, game_over)
-        }
+        (motions, merges)
     }
 
-    fn game_over(self) -> bool {
-        // mutable => contains 0
-        let mutable = self.matrix.iter().any(|e| *e == 0);
-        // combinable
-        !mutable && !self.matrix.any_col(Self::combinable) && !self.matrix.any_row(Self::combinable)
+    fn set_line(&mut self, index: usize, dim: Dimension, line: &[u32]) {
+        if dim == Dimension::Col {
+            self.matrix
+                .set_column(index, &DVector::from_row_slice(line));
+        } else {
+            self.matrix
+                .set_row(index, &RowDVector::from_row_slice(line));
+        }
     }
 
-    // if there was no combination, replace a 0 with next tile
-    fn force_insert_next_tile<R: Rng + ?Sized>(
-        r: &mut R,
-        matrix: SMatrix<u32, 4, 4>,
-        index: usize,
-        dim: Dimension,
-        next_tile: u32,
-    ) -> Option<Vec<u32>> {
-        match Self::get_line(matrix, index, dim) {
-            Some(slice) => {
-                let mut zeros = Vec::with_capacity(slice.len());
-                for i in 0..slice.len() {
-                    if slice[i] == 0 {
-                        zeros.push(i);
-                    }
-                }
-                if zeros.len() == 0 {
-                    None
-                } else {
-                    let i = r.sample(Uniform::new(0, zeros.len()));
-                    let idx = zeros[i];
-                    let mut values = slice.to_vec();
-                    let _ = std::mem::replace(&mut values[idx], next_tile);
-                    Some(values.as_slice().into())
+    // a direction is legal if shifting in it moves at least one line
+    pub fn can_shift(&self, rules: &dyn Rules, dir: Direction) -> bool {
+        let reverse_needed = dir.reverse_needed();
+        let dim = dir.associated_dimension();
+        (0..self.lines(dim)).any(|i| match Self::get_line(&self.matrix, i, dim) {
+            Some(mut elements) => {
+                if reverse_needed {
+                    elements.reverse()
                 }
+                let (new_line, _, _) = rules.shift_line(&elements);
+                new_line != elements
             }
-            None => None,
-        }
+            None => false,
+        })
+    }
+
+    pub fn legal_moves(&self, rules: &dyn Rules) -> Vec<Direction> {
+        Direction::ALL
+            .into_iter()
+            .filter(|d| self.can_shift(rules, *d))
+            .collect()
+    }
+
+    pub fn empty_cells(&self) -> usize {
+        self.matrix.iter().filter(|e| **e == 0).count()
+    }
+
+    // number of adjacent tiles which could combine
+    pub fn merges(&self, rules: &dyn Rules) -> usize {
+        let count = |line: &[u32]| {
+            line.windows(2)
+                .filter(|w| rules.combine(w[0], w[1]).is_some())
+                .count()
+        };
+        let cols: usize = self.matrix.column_iter().map(|c| count(c.as_slice())).sum();
+        // row views are not contiguous, hence the clone_owned
+        let rows: usize = self
+            .matrix
+            .row_iter()
+            .map(|r| count(r.clone_owned().as_slice()))
+            .sum();
+        cols + rows
+    }
+
+    pub fn score(&self, rules: &dyn Rules) -> u64 {
+        self.matrix.fold(0, |acc, e| acc + rules.tile_score(e))
+    }
+
+    // no move is left
+    pub fn game_over(&self, rules: &dyn Rules) -> bool {
+        // mutable => contains 0
+        let mutable = self.matrix.iter().any(|e| *e == 0);
+        let combinable = |line: &[u32]| Self::combinable(rules, line);
+        !mutable && !self.matrix.any_col(combinable) && !self.matrix.any_row(combinable)
     }
 
-    fn get_line(matrix: SMatrix<u32, 4, 4>, index: usize, dim: Dimension) -> Option<Vec<u32>> {
+    fn get_line(matrix: &DMatrix<u32>, index: usize, dim: Dimension) -> Option<Vec<u32>> {
         if dim == Dimension::Col && index < matrix.ncols() {
             let col = matrix.column(index);
             Some(col.as_slice().into())
@@ -140,124 +311,52 @@ impl Grid {
         }
     }
 
-    fn shift_line(
-        elements: &[u32],
-        next_tile: u32,
-        next_tile_inserted: bool,
-    ) -> (Vec<u32>, bool, bool) {
-        fn inner(
-            elements: &[u32],
-            mut acc: Vec<u32>,
-            mutated: bool,
-            combined: bool,
-            combiner: &dyn Fn(u32, u32) -> Option<u32>,
-        ) -> (Vec<u32>, bool, bool) {
-            if !combined {
-                match elements {
-                    [h1, h2, t @ ..] => {
-                        if let Some(value) = combiner(*h1, *h2) {
-                            acc.push(value);
-                            inner(t, acc, true, true, combiner)
-                        } else if h1 == &0 {
-                            acc.push(*h2);
-                            // todo: find a way to avoid the vec allocation
-                            let mut es: Vec<u32> = t.to_vec();
-                            es.insert(0, 0);
-                            inner(es.as_slice(), acc, true, combined, combiner)
-                        } else {
-                            acc.push(*h1);
-                            inner(&elements[1..], acc, mutated, combined, combiner)
-                        }
-                    }
-                    [h, t @ ..] => {
-                        acc.push(*h);
-                        inner(t, acc, mutated, combined, combiner)
-                    }
-                    _ => (acc, mutated, combined),
-                }
-            } else {
-                // todo: find a way to short-circuit
-                match elements {
-                    [h, t @ ..] => {
-                        acc.push(*h);
-                        inner(t, acc, mutated, combined, combiner)
-                    }
-                    _ => (acc, mutated, combined),
-                }
-            }
-        }
-
-        let (mut res, mutated, combined) = inner(
-            elements,
-            Vec::with_capacity(elements.len()),
-            false,
-            false,
-            &Self::combiner,
-        );
-        if combined {
-            if next_tile_inserted {
-                res.push(0);
-            } else {
-                res.push(next_tile);
-            }
-            (res, mutated, combined)
-        } else {
-            (res, mutated, combined)
-        }
+    fn combinable(rules: &dyn Rules, elements: &[u32]) -> bool {
+        elements
+            .windows(2)
+            .any(|w| rules.combine(w[0], w[1]).is_some())
     }
+}
 
-    fn combiner(h1: u32, h2: u32) -> Option<u32> {
-        if h1 == h2 && h1 > 2 {
-            Some(h1 * 2)
-        } else if h1 + h2 == 3 && h1 < 3 && h2 < 3 {
-            Some(h1 + h2)
-        } else {
-            None
+// fixed size matrices make for readable positions
+impl<const R: usize, const C: usize> From<SMatrix<u32, R, C>> for Grid {
+    fn from(matrix: SMatrix<u32, R, C>) -> Grid {
+        Grid {
+            matrix: DMatrix::from_iterator(R, C, matrix.iter().copied()),
         }
     }
-
-    fn combinable(elements: &[u32]) -> bool {
-        fn inner(es: &[u32], acc: bool, f: &dyn Fn(u32, u32) -> bool) -> bool {
-            if acc {
-                acc
-            } else {
-                match es {
-                    [h1, h2, _t @ ..] => {
-                        if f(*h1, *h2) {
-                            true
-                        } else {
-                            inner(&es[1..], acc, f)
-                        }
-                    }
-                    _ => false,
-                }
-            }
-        }
-        inner(elements, false, &|h1, h2| Self::combiner(h1, h2).is_some())
-    }
 }
 
 #[cfg(test)]
 mod tests {
-    use rand::rngs::OsRng;
+    use crate::{
+        rng,
+        rules::{insertion::Insertion, threes::Threes, twenty_forty_eight::TwentyFortyEight},
+    };
 
     use super::*;
 
-    fn new_grid(matrix: SMatrix<u32, 4, 4>) -> Grid {
-        Grid { matrix }
+    use nalgebra::Matrix4;
+
+    fn new_grid(matrix: Matrix4<u32>) -> Grid {
+        Grid::from(matrix)
+    }
+
+    fn new_matrix(matrix: Matrix4<u32>) -> DMatrix<u32> {
+        new_grid(matrix).matrix
     }
 
     #[test]
     fn combinable_true_if_identical_ge_3() -> () {
         let slice = &[1, 3, 3, 1];
-        let res = Grid::combinable(slice);
+        let res = Grid::combinable(&Threes::default(), slice);
         assert!(res);
     }
 
     #[test]
     fn combinable_true_if_1_2() -> () {
         let slice = &[1, 2, 3, 1];
-        let res = Grid::combinable(slice);
+        let res = Grid::combinable(&Threes::default(), slice);
         assert!(res);
     }
 
@@ -266,7 +365,7 @@ mod tests {
         let mut m = Matrix4::repeat(1);
         m[(1, 3)] = 0;
         let g = new_grid(m);
-        assert!(!g.game_over());
+        assert!(!g.game_over(&Threes::default()));
     }
 
     #[test]
@@ -275,7 +374,7 @@ mod tests {
         m[(1, 0)] = 3;
         m[(2, 0)] = 3;
         let g = new_grid(m);
-        assert!(!g.game_over());
+        assert!(!g.game_over(&Threes::default()));
     }
 
     #[test]
@@ -284,207 +383,391 @@ mod tests {
         m[(1, 0)] = 3;
         m[(1, 1)] = 3;
         let g = new_grid(m);
-        assert!(!g.game_over());
+        assert!(!g.game_over(&Threes::default()));
+    }
+
+    // spawns at the end of the first combined line, otherwise on any free cell of the edge
+    fn first_combined() -> Threes {
+        Threes {
+            insertion: Insertion::FirstCombined,
+            ..Threes::default()
+        }
+    }
+
+    fn slide(m: Matrix4<u32>, direction: Direction) -> Slide {
+        Slide {
+            grid: new_grid(m),
+            direction,
+            moved: vec![0],
+            combined: Vec::new(),
+        }
     }
 
     #[test]
-    fn force_insert_next_tile_some_if_zero_found_up() -> () {
-        let mut r = OsRng;
+    fn spawn_cells_some_if_zero_found_up() {
         let mut m = Matrix4::repeat(1);
         m[(3, 3)] = 0;
-        let res = Grid::force_insert_next_tile(&mut r, m, 3, Dimension::Col, 12);
-        let exp = vec![1, 1, 1, 12];
-        assert_eq!(res, Some(exp));
+        assert_eq!(
+            first_combined().spawn_cells(&slide(m, Direction::Up)),
+            vec![(3, 3)]
+        );
     }
 
     #[test]
-    fn force_insert_next_tile_some_if_zero_found_down() -> () {
-        let mut r = OsRng;
+    fn spawn_cells_some_if_zero_found_down() {
         let mut m = Matrix4::repeat(1);
-        m[(3, 0)] = 0;
-        let res = Grid::force_insert_next_tile(&mut r, m, 0, Dimension::Col, 12);
-        let exp = vec![1, 1, 1, 12];
-        assert_eq!(res, Some(exp));
+        m[(0, 3)] = 0;
+        assert_eq!(
+            first_combined().spawn_cells(&slide(m, Direction::Down)),
+            vec![(0, 3)]
+        );
     }
 
     #[test]
-    fn force_insert_next_tile_some_if_zero_found_left() -> () {
-        let mut r = OsRng;
+    fn spawn_cells_some_if_zero_found_left() {
         let mut m = Matrix4::repeat(1);
         m[(0, 3)] = 0;
-        let res = Grid::force_insert_next_tile(&mut r, m, 0, Dimension::Row, 12);
-        let exp = vec![1, 1, 1, 12];
-        assert_eq!(res, Some(exp));
+        assert_eq!(
+            first_combined().spawn_cells(&slide(m, Direction::Left)),
+            vec![(0, 3)]
+        );
     }
 
     #[test]
-    fn force_insert_next_tile_some_if_zero_found_right() -> () {
-        let mut r = OsRng;
+    fn spawn_cells_some_if_zero_found_right() {
         let mut m = Matrix4::repeat(1);
-        m[(3, 3)] = 0;
-        let res = Grid::force_insert_next_tile(&mut r, m, 3, Dimension::Row, 12);
-        let exp = vec![1, 1, 1, 12];
-        assert_eq!(res, Some(exp));
+        m[(3, 0)] = 0;
+        assert_eq!(
+            first_combined().spawn_cells(&slide(m, Direction::Right)),
+            vec![(3, 0)]
+        );
     }
 
     #[test]
-    fn force_insert_next_tile_none_if_idx_out_of_bounds() -> () {
-        let mut r = OsRng;
+    fn spawn_cells_none_if_no_zeros() {
         let m = Matrix4::repeat(1);
-        let res = Grid::force_insert_next_tile(&mut r, m, 4, Dimension::Col, 12);
-        assert_eq!(res, None);
+        for d in Direction::ALL {
+            assert!(first_combined().spawn_cells(&slide(m, d)).is_empty());
+        }
     }
 
     #[test]
-    fn force_insert_next_tile_none_if_no_zeros() -> () {
-        let mut r = OsRng;
-        let m = Matrix4::repeat(1);
-        let res_up = Grid::force_insert_next_tile(&mut r, m, 3, Dimension::Col, 12);
-        assert_eq!(res_up, None);
-        let res_down = Grid::force_insert_next_tile(&mut r, m, 0, Dimension::Col, 12);
-        assert_eq!(res_down, None);
-        let res_left = Grid::force_insert_next_tile(&mut r, m, 3, Dimension::Row, 12);
-        assert_eq!(res_left, None);
-        let res_right = Grid::force_insert_next_tile(&mut r, m, 0, Dimension::Row, 12);
-        assert_eq!(res_right, None);
+    fn spawn_cells_end_of_first_combined_line() {
+        let mut s = slide(Matrix4::repeat(0), Direction::Left);
+        s.combined = vec![2, 3];
+        assert_eq!(first_combined().spawn_cells(&s), vec![(2, 3)]);
     }
 
     #[test]
     fn shift_grid_does_one_transformation_reversed_per_col() -> () {
-        let mut r = OsRng;
+        let mut r = rng::from_seed(42);
         let m = Matrix4::new(1, 1, 1, 1, 2, 2, 2, 2, 1, 1, 1, 1, 2, 2, 2, 2);
         let g = new_grid(m);
-        let (res, _, _) = g.shift(&mut r, Direction::Down, 12);
+        let res = g
+            .shift(&Threes::default(), &mut r, Direction::Down, 12)
+            .grid;
         let expected = Matrix4::new(12, 0, 0, 0, 1, 1, 1, 1, 2, 2, 2, 2, 3, 3, 3, 3);
         assert_eq!(res.matrix, expected);
     }
 
     #[test]
     fn shift_grid_does_one_transformation_reversed_per_row() -> () {
-        let mut r = OsRng;
+        let mut r = rng::from_seed(42);
         let m = Matrix4::new(1, 2, 1, 2, 1, 2, 1, 2, 1, 2, 1, 2, 1, 2, 1, 2);
         let g = new_grid(m);
-        let (res, _, _) = g.shift(&mut r, Direction::Right, 12);
+        let res = g
+            .shift(&Threes::default(), &mut r, Direction::Right, 12)
+            .grid;
         let expected = Matrix4::new(12, 1, 2, 3, 0, 1, 2, 3, 0, 1, 2, 3, 0, 1, 2, 3);
         assert_eq!(res.matrix, expected);
     }
 
     #[test]
     fn shift_grid_does_no_more_than_one_transformation_per_col() -> () {
-        let mut r = OsRng;
+        let mut r = rng::from_seed(42);
         let m = Matrix4::new(1, 1, 1, 1, 2, 2, 2, 2, 1, 1, 1, 1, 2, 2, 2, 2);
         let g = new_grid(m);
-        let (res, _, _) = g.shift(&mut r, Direction::Up, 12);
+        let res = g.shift(&Threes::default(), &mut r, Direction::Up, 12).grid;
         let expected = Matrix4::new(3, 3, 3, 3, 1, 1, 1, 1, 2, 2, 2, 2, 12, 0, 0, 0);
         assert_eq!(res.matrix, expected);
     }
 
     #[test]
     fn shift_grid_does_no_more_than_one_transformation_per_row() -> () {
-        let mut r = OsRng;
+        let mut r = rng::from_seed(42);
         let m = Matrix4::new(1, 2, 1, 2, 1, 2, 1, 2, 1, 2, 1, 2, 1, 2, 1, 2);
         let g = new_grid(m);
-        let (res, _, _) = g.shift(&mut r, Direction::Left, 12);
+        let res = g
+            .shift(&Threes::default(), &mut r, Direction::Left, 12)
+            .grid;
         let expected = Matrix4::new(3, 1, 2, 12, 3, 1, 2, 0, 3, 1, 2, 0, 3, 1, 2, 0);
         assert_eq!(res.matrix, expected);
     }
 
     #[test]
     fn shift_grid_does_not_mutate_if_immutable() -> () {
-        let mut r = OsRng;
+        let mut r = rng::from_seed(42);
         let m = Matrix4::repeat(1);
         let g = new_grid(m);
-        let (res, _, _) = g.shift(&mut r, Direction::Up, 12);
+        let res = g.shift(&Threes::default(), &mut r, Direction::Up, 12).grid;
         assert_eq!(res.matrix, m);
     }
 
     #[test]
-    fn get_line_none_if_index_is_oob_row() -> () {
-        let m = Matrix4::repeat(1);
-        let res = Grid::get_line(m, 4, Dimension::Row);
-        assert_eq!(res, None);
+    fn can_shift_false_if_immutable() {
+        let g = new_grid(Matrix4::repeat(1));
+        assert!(Direction::ALL
+            .iter()
+            .all(|d| !g.can_shift(&Threes::default(), *d)));
     }
 
     #[test]
-    fn get_line_none_if_index_is_oob_col() -> () {
-        let m = Matrix4::repeat(1);
-        let res = Grid::get_line(m, 4, Dimension::Col);
-        assert_eq!(res, None);
+    fn can_shift_only_towards_free_cells() {
+        let m = Matrix4::new(0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 3, 6, 12, 24);
+        let g = new_grid(m);
+        assert!(g.can_shift(&Threes::default(), Direction::Up));
+        assert!(!g.can_shift(&Threes::default(), Direction::Down));
+        assert!(!g.can_shift(&Threes::default(), Direction::Left));
+        assert!(!g.can_shift(&Threes::default(), Direction::Right));
     }
 
     #[test]
-    fn get_line_should_return_col_if_dim_is_col() -> () {
-        let m = Matrix4::new(1, 0, 0, 0, 1, 0, 0, 0, 1, 0, 0, 0, 1, 0, 0, 0);
-        let res = Grid::get_line(m, 0, Dimension::Col);
-        let expected = vec![1, 1, 1, 1];
-        assert_eq!(res, Some(expected));
+    fn shift_outcomes_is_empty_if_illegal() {
+        let g = new_grid(Matrix4::repeat(1));
+        assert!(g
+            .shift_outcomes(&Threes::default(), Direction::Up, 12)
+            .is_empty());
     }
 
     #[test]
-    fn get_line_should_return_row_if_dim_is_row() -> () {
-        let m = Matrix4::new(0, 0, 0, 0, 1, 1, 1, 1, 0, 0, 0, 0, 0, 0, 0, 0);
-        let res = Grid::get_line(m, 1, Dimension::Row);
-        let expected = vec![1, 1, 1, 1];
-        assert_eq!(res, Some(expected));
+    fn shift_outcomes_is_certain_if_combined() {
+        let m = Matrix4::new(1, 1, 1, 1, 2, 2, 2, 2, 1, 1, 1, 1, 2, 2, 2, 2);
+        let g = new_grid(m);
+        let mut r = rng::from_seed(42);
+        let shifted = g.shift(&first_combined(), &mut r, Direction::Up, 12).grid;
+        assert_eq!(
+            g.shift_outcomes(&first_combined(), Direction::Up, 12),
+            vec![(shifted, 1.0)]
+        );
+    }
+
+    #[test]
+    fn shift_outcomes_lists_every_free_edge_cell() {
+        let m = Matrix4::new(0, 0, 0, 0, 3, 0, 0, 0, 0, 0, 0, 0, 6, 0, 0, 0);
+        let g = new_grid(m);
+        let outcomes = g.shift_outcomes(&first_combined(), Direction::Up, 12);
+        assert_eq!(outcomes.len(), 4);
+        assert!(outcomes.iter().all(|(_, p)| *p == 0.25));
+        assert!(outcomes.iter().all(|(g, _)| g.matrix[(0, 0)] == 3));
+        for j in 0..4 {
+            assert!(outcomes.iter().any(|(g, _)| g.matrix[(3, j)] == 12));
+        }
+        let mut r = rng::from_seed(42);
+        let shifted = g.shift(&first_combined(), &mut r, Direction::Up, 12).grid;
+        assert!(outcomes.iter().any(|(g, _)| *g == shifted));
+    }
+
+    #[test]
+    fn shift_follows_every_tile() {
+        let g = new_grid(Matrix4::new(0, 3, 0, 0, 1, 2, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0));
+        let mut r = rng::from_seed(42);
+        let outcome = g.shift(&first_combined(), &mut r, Direction::Left, 2);
+        assert_eq!(
+            outcome.motions,
+            vec![
+                Motion {
+                    from: (0, 1),
+                    to: (0, 0),
+                    value: 3
+                },
+                Motion {
+                    from: (1, 0),
+                    to: (1, 0),
+                    value: 1
+                },
+                Motion {
+                    from: (1, 1),
+                    to: (1, 0),
+                    value: 2
+                },
+            ]
+        );
+        assert_eq!(
+            outcome.merges,
+            vec![Merge {
+                at: (1, 0),
+                value: 3
+            }]
+        );
+        assert_eq!(
+            outcome.spawned,
+            Some(Spawn {
+                at: (1, 3),
+                value: 2
+            })
+        );
+        // the 3 made out of a 1 and a 2
+        assert_eq!(outcome.score_delta, 3);
+        assert!(!outcome.is_noop());
+        assert!(!outcome.game_over);
+    }
+
+    #[test]
+    fn shift_follows_tiles_reversed_along_columns() {
+        let g = new_grid(Matrix4::new(3, 0, 0, 0, 3, 0, 0, 0, 6, 0, 0, 0, 0, 0, 0, 0));
+        let mut r = rng::from_seed(42);
+        let outcome = g.shift(&Threes::default(), &mut r, Direction::Down, 1);
+        assert_eq!(
+            outcome
+                .motions
+                .iter()
+                .map(|m| (m.from, m.to))
+                .collect::<Vec<_>>(),
+            vec![((2, 0), (3, 0)), ((1, 0), (2, 0)), ((0, 0), (1, 0))]
+        );
+        assert!(outcome.merges.is_empty());
+        assert_eq!(outcome.spawned.map(|s| s.at), Some((0, 0)));
+    }
+
+    #[test]
+    fn shift_merges_tiles_moving_several_cells() {
+        let m = Matrix4::new(2, 0, 2, 4, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0);
+        let g = new_grid(m);
+        let mut r = rng::from_seed(42);
+        let outcome = g.shift(&TwentyFortyEight, &mut r, Direction::Left, 2);
+        assert_eq!(
+            outcome
+                .motions
+                .iter()
+                .map(|m| (m.from, m.to))
+                .collect::<Vec<_>>(),
+            vec![((0, 0), (0, 0)), ((0, 2), (0, 0)), ((0, 3), (0, 1))]
+        );
+        assert_eq!(
+            outcome.merges,
+            vec![Merge {
+                at: (0, 0),
+                value: 4
+            }]
+        );
+        assert_eq!(outcome.score_delta, 4);
+    }
+
+    #[test]
+    fn shift_reports_game_over_once_stuck() {
+        let m = Matrix4::new(12, 24, 12, 0, 6, 3, 6, 3, 3, 6, 3, 6, 6, 3, 6, 3);
+        let g = new_grid(m);
+        assert_eq!(
+            g.legal_moves(&Threes::default()),
+            vec![Direction::Up, Direction::Right]
+        );
+        let mut r = rng::from_seed(42);
+        let outcome = g.shift(&Threes::default(), &mut r, Direction::Right, 1);
+        assert!(outcome.spawned.is_some());
+        assert!(outcome.game_over);
+        assert!(outcome.grid.legal_moves(&Threes::default()).is_empty());
+    }
+
+    #[test]
+    fn empty_cells_sliding_over_each_other_are_no_move() {
+        let m = Matrix4::new(3, 0, 0, 0, 6, 0, 0, 0, 12, 0, 0, 0, 24, 0, 0, 0);
+        let g = new_grid(m);
+        assert_eq!(g.legal_moves(&Threes::default()), vec![Direction::Right]);
+        let mut r = rng::from_seed(42);
+        assert!(g
+            .shift(&Threes::default(), &mut r, Direction::Left, 1)
+            .is_noop());
+    }
+
+    #[test]
+    fn shift_is_a_noop_if_nothing_moves() {
+        let g = new_grid(Matrix4::new(3, 6, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0));
+        let mut r = rng::from_seed(42);
+        let outcome = g.shift(&Threes::default(), &mut r, Direction::Left, 2);
+        assert!(outcome.is_noop());
+        assert_eq!(outcome.grid, g);
+        assert_eq!(outcome.spawned, None);
+        assert_eq!(outcome.score_delta, 0);
+    }
+
+    #[test]
+    fn rand_fills_the_requested_size() {
+        let mut r = rng::from_seed(42);
+        let size = Size { cols: 5, rows: 3 };
+        let g = Grid::rand(&mut r, [2, 1, 1, 1], size);
+        assert_eq!(g.size(), size);
+        assert_eq!(g.matrix.nrows(), 3);
+        assert_eq!(g.matrix.ncols(), 5);
+    }
+
+    #[test]
+    fn shift_inserts_next_tile_at_the_edge_of_rectangular_grids() {
+        let mut r = rng::from_seed(42);
+        // 3 columns, 2 rows
+        let m = nalgebra::Matrix2x3::new(0, 3, 0, 0, 0, 6);
+        let g = Grid::from(m);
+        let outcome = g.shift(&Threes::default(), &mut r, Direction::Left, 12);
+        assert!(outcome.spawned.is_some());
+        let res = outcome.grid;
+        assert_eq!(res.matrix[(0, 0)], 3);
+        assert_eq!(res.matrix[(1, 1)], 6);
+        assert_eq!(res.matrix[(0, 2)] + res.matrix[(1, 2)], 12);
+        let outcome = g.shift(&Threes::default(), &mut r, Direction::Up, 12);
+        assert!(outcome.spawned.is_some());
+        let res = outcome.grid;
+        assert_eq!(res.matrix[(0, 2)], 6);
+        assert_eq!(res.matrix.row(1).sum(), 12);
+    }
+
+    #[test]
+    fn can_shift_checks_every_line_of_rectangular_grids() {
+        // 2 columns, 5 rows, only the last row can move
+        let m = nalgebra::SMatrix::<u32, 5, 2>::new(3, 6, 3, 6, 3, 6, 3, 6, 0, 6);
+        let g = Grid::from(m);
+        assert!(g.can_shift(&Threes::default(), Direction::Left));
+        assert!(g.can_shift(&Threes::default(), Direction::Down));
+        assert!(!g.can_shift(&Threes::default(), Direction::Right));
     }
 
     #[test]
-    fn shift_line_mutate_zeros() -> () {
-        let array = [1, 0, 2, 2];
-        let (res, mutated, combined) = Grid::shift_line(&array, 12, true);
-        assert!(mutated);
-        assert!(!combined);
-        let expected = vec![1, 2, 2, 0];
-        assert_eq!(res, expected);
+    fn merges_counts_adjacent_combinations() {
+        let m = Matrix4::new(1, 2, 0, 0, 0, 0, 0, 0, 3, 0, 0, 0, 3, 6, 6, 0);
+        assert_eq!(new_grid(m).merges(&Threes::default()), 3);
     }
 
     #[test]
-    fn shift_line_should_not_mutate_if_immutable() -> () {
-        let array = [3, 6, 9, 12];
-        let (res, mutated, combined) = Grid::shift_line(&array, 12, true);
-        assert!(!mutated);
-        assert!(!combined);
-        let expected: Vec<u32> = array.into();
-        assert_eq!(res, expected);
+    fn empty_cells_counts_zeros() {
+        let m = Matrix4::new(1, 2, 0, 0, 0, 0, 0, 0, 3, 0, 0, 0, 3, 6, 6, 0);
+        assert_eq!(new_grid(m).empty_cells(), 10);
     }
 
     #[test]
-    fn shift_line_should_combine_if_adjacent_are_same() -> () {
-        let array = [12, 12, 3, 6];
-        let (res, mutated, combined) = Grid::shift_line(&array, 12, true);
-        assert!(mutated);
-        assert!(combined);
-        let expected = vec![24, 3, 6, 0];
-        assert_eq!(res, expected);
+    fn get_line_none_if_index_is_oob_row() -> () {
+        let m = Matrix4::repeat(1);
+        let res = Grid::get_line(&new_matrix(m), 4, Dimension::Row);
+        assert_eq!(res, None);
     }
 
     #[test]
-    fn shift_line_should_combine_only_once() -> () {
-        let array = [12, 12, 6, 6];
-        let (res, mutated, combined) = Grid::shift_line(&array, 12, true);
-        assert!(mutated);
-        assert!(combined);
-        let expected = vec![24, 6, 6, 0];
-        assert_eq!(res, expected);
+    fn get_line_none_if_index_is_oob_col() -> () {
+        let m = Matrix4::repeat(1);
+        let res = Grid::get_line(&new_matrix(m), 4, Dimension::Col);
+        assert_eq!(res, None);
     }
 
     #[test]
-    fn shift_line_should_combine_1_2() -> () {
-        let array = [1, 2, 6, 6];
-        let (res, mutated, combined) = Grid::shift_line(&array, 12, true);
-        assert!(mutated);
-        assert!(combined);
-        let expected = vec![3, 6, 6, 0];
-        assert_eq!(res, expected);
+    fn get_line_should_return_col_if_dim_is_col() -> () {
+        let m = Matrix4::new(1, 0, 0, 0, 1, 0, 0, 0, 1, 0, 0, 0, 1, 0, 0, 0);
+        let res = Grid::get_line(&new_matrix(m), 0, Dimension::Col);
+        let expected = vec![1, 1, 1, 1];
+        assert_eq!(res, Some(expected));
     }
 
     #[test]
-    fn shift_line_should_combine_2_1() -> () {
-        let array = [2, 1, 6, 6];
-        let (res, mutated, combined) = Grid::shift_line(&array, 12, true);
-        assert!(mutated);
-        assert!(combined);
-        let expected = vec![3, 6, 6, 0];
-        assert_eq!(res, expected);
+    fn get_line_should_return_row_if_dim_is_row() -> () {
+        let m = Matrix4::new(0, 0, 0, 0, 1, 1, 1, 1, 0, 0, 0, 0, 0, 0, 0, 0);
+        let res = Grid::get_line(&new_matrix(m), 1, Dimension::Row);
+        let expected = vec![1, 1, 1, 1];
+        assert_eq!(res, Some(expected));
     }
 }
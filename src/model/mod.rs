@@ -1,5 +1,9 @@
+pub mod bitboard;
 mod buckets;
+pub mod deck;
 pub mod dimension;
 pub mod direction;
 pub mod grid;
+pub mod outcome;
+pub mod size;
 pub mod tile;
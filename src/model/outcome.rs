@@ -0,0 +1,43 @@
+use super::{direction::Direction, grid::Grid};
+
+// a tile of the grid before a move and the cell it ended up in, tiles which didn't move
+// included
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Motion {
+    pub from: (usize, usize),
+    pub to: (usize, usize),
+    pub value: u32,
+}
+
+// two tiles which combined in a cell, into a tile of this value
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Merge {
+    pub at: (usize, usize),
+    pub value: u32,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Spawn {
+    pub at: (usize, usize),
+    pub value: u32,
+}
+
+// everything a move did to the grid, tile by tile
+#[derive(Debug, Clone, PartialEq)]
+pub struct MoveOutcome {
+    pub grid: Grid,
+    pub direction: Direction,
+    pub motions: Vec<Motion>,
+    pub merges: Vec<Merge>,
+    pub spawned: Option<Spawn>,
+    // what the move added to the score, the new tile included
+    pub score_delta: u64,
+    pub game_over: bool,
+}
+
+impl MoveOutcome {
+    // nothing moved, the move was not legal
+    pub fn is_noop(&self) -> bool {
+        self.motions.iter().all(|m| m.from == m.to)
+    }
+}
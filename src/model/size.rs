@@ -0,0 +1,95 @@
+use std::{fmt, str::FromStr};
+
+use anyhow::{bail, Context, Error, Result};
+use serde::{Deserialize, Serialize};
+
+// board dimensions, written as `<columns>x<rows>`
+#[derive(Debug, PartialEq, Eq, Copy, Clone, Hash, Serialize, Deserialize)]
+pub struct Size {
+    pub cols: usize,
+    pub rows: usize,
+}
+
+impl Size {
+    // a line needs two cells to combine anything, the ui can't fit more than 8 tiles
+    pub const MIN: usize = 2;
+    pub const MAX: usize = 8;
+
+    pub fn new(cols: usize, rows: usize) -> Result<Size> {
+        for (name, n) in [("columns", cols), ("rows", rows)] {
+            if !(Self::MIN..=Self::MAX).contains(&n) {
+                bail!(
+                    "expected between {} and {} {}, found {}",
+                    Self::MIN,
+                    Self::MAX,
+                    name,
+                    n
+                );
+            }
+        }
+        Ok(Size { cols, rows })
+    }
+
+    pub fn cells(self) -> usize {
+        self.cols * self.rows
+    }
+}
+
+impl Default for Size {
+    fn default() -> Size {
+        Size { cols: 4, rows: 4 }
+    }
+}
+
+impl fmt::Display for Size {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}x{}", self.cols, self.rows)
+    }
+}
+
+impl FromStr for Size {
+    type Err = Error;
+
+    fn from_str(s: &str) -> Result<Size> {
+        let (cols, rows) = s
+            .trim()
+            .split_once(['x', 'X'])
+            .with_context(|| format!("expected <columns>x<rows>, found `{}`", s))?;
+        let parse = |n: &str| {
+            n.trim()
+                .parse::<usize>()
+                .with_context(|| format!("expected a number, found `{}`", n))
+        };
+        Size::new(parse(cols)?, parse(rows)?)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn default_is_4x4() {
+        assert_eq!(Size::default().to_string(), "4x4");
+        assert_eq!(Size::default().cells(), 16);
+    }
+
+    #[test]
+    fn parses_columns_then_rows() {
+        let size: Size = "5x3".parse().unwrap();
+        assert_eq!(size, Size { cols: 5, rows: 3 });
+        assert_eq!(size.to_string(), "5x3");
+    }
+
+    #[test]
+    fn rejects_out_of_range_sizes() {
+        assert!("1x4".parse::<Size>().is_err());
+        assert!("4x9".parse::<Size>().is_err());
+    }
+
+    #[test]
+    fn rejects_malformed_sizes() {
+        assert!("4".parse::<Size>().is_err());
+        assert!("4xa".parse::<Size>().is_err());
+    }
+}
@@ -1,20 +1,70 @@
 use rand::{distributions::Uniform, Rng};
+use serde::{Deserialize, Serialize};
 
-use crate::math::{distribution::Distribution, series::Series};
+use crate::{
+    math::{distribution::Distribution, series::Series},
+    rng,
+};
 
-#[derive(Clone, Copy)]
+use super::deck::Deck;
+
+// once the highest tile reaches BONUS_THRESHOLD, one tile out of BONUS_ODDS is a bonus one,
+// anything from 6 to an eighth of the highest tile
+const BONUS_THRESHOLD: u32 = 48;
+const BONUS_ODDS: u32 = 21;
+const BONUS_MIN: u32 = 6;
+// number of values a bonus tile is previewed as, the actual one among them
+const PREVIEW_SIZE: usize = 3;
+
+#[derive(Clone, Copy, Serialize, Deserialize)]
 pub struct Tile {
     value: u32,
     series: Series,
     distribution: Distribution,
+    // only drawn from by draw
+    deck: Deck,
+    // lowest and highest values a bonus tile is previewed as, none for other tiles
+    #[serde(default)]
+    bonus: Option<(u32, u32)>,
 }
 
 impl Tile {
     pub fn new<R: Rng + ?Sized>(r: &mut R) -> Tile {
+        Self::from_value(r.sample(Uniform::new(1, 3)))
+    }
+
+    // the first card of a new deck
+    pub fn from_deck<R: Rng + ?Sized>(r: &mut R) -> Tile {
+        let mut deck = Deck::default();
+        let value = deck.draw(r);
         Tile {
-            value: r.sample(Uniform::new(1, 3)),
+            deck,
+            ..Self::from_value(value)
+        }
+    }
+
+    pub fn from_value(value: u32) -> Tile {
+        Tile {
+            value,
             series: Series::new(1, 2, 2),
             distribution: Distribution::new(0.5),
+            deck: Deck::default(),
+            bonus: None,
+        }
+    }
+
+    // a tile previewed as these candidates, its value drawn among them
+    pub fn from_preview<R: Rng + ?Sized>(r: &mut R, candidates: &[u32]) -> Tile {
+        match candidates {
+            [value] => Self::from_value(*value),
+            [lowest, .., highest] => {
+                let i = rng::index(r, candidates.len());
+                Tile {
+                    bonus: Some((*lowest, *highest)),
+                    ..Self::from_value(candidates[i])
+                }
+            }
+            [] => panic!("a tile needs at least one candidate"),
         }
     }
 
@@ -22,10 +72,26 @@ impl Tile {
         self.value
     }
 
+    pub fn is_bonus(&self) -> bool {
+        self.bonus.is_some()
+    }
+
+    // what the tile is shown as before it is placed, a few candidates for bonus tiles
+    pub fn preview(&self) -> Vec<u32> {
+        match self.bonus {
+            Some((lowest, highest)) => {
+                std::iter::successors(Some(lowest), |v| Some(v * 2).filter(|v| *v <= highest))
+                    .collect()
+            }
+            None => vec![self.value],
+        }
+    }
+
     // 1/3 0
     // 1/3 1
     // 1/3 x
     pub fn next<R: Rng + ?Sized>(mut self, r: &mut R, max: u32) -> Tile {
+        self.bonus = None;
         let sampled = r.sample(Uniform::new(0, 3));
         if sampled < 2 {
             self.value = self.series.u_n(sampled);
@@ -36,6 +102,108 @@ impl Tile {
         self
     }
 
+    // draws the next card of the deck, or a bonus tile
+    pub fn draw<R: Rng + ?Sized>(mut self, r: &mut R, max: u32) -> Tile {
+        let bonuses = self.bonus_values(max);
+        if !bonuses.is_empty() && r.sample(Uniform::new(0, BONUS_ODDS)) == 0 {
+            let i = rng::index(r, bonuses.len());
+            self.value = bonuses[i];
+            // the window is placed at random around the value not to give it away
+            let size = bonuses.len().min(PREVIEW_SIZE);
+            let first = i.saturating_sub(size - 1) as u32;
+            let last = i.min(bonuses.len() - size) as u32;
+            let start = r.sample(Uniform::new_inclusive(first, last)) as usize;
+            self.bonus = Some((bonuses[start], bonuses[start + size - 1]));
+        } else {
+            self.value = self.deck.draw(r);
+            self.bonus = None;
+        }
+        self
+    }
+
+    // every tile draw can return, with its probability
+    pub fn draw_outcomes(&self, max: u32) -> Vec<(Tile, f64)> {
+        let bonuses = self.bonus_values(max);
+        let p_bonus = if bonuses.is_empty() {
+            0.0
+        } else {
+            1.0 / BONUS_ODDS as f64
+        };
+        let cards = self.deck.outcomes().into_iter().map(|(value, deck, p)| {
+            let tile = Tile {
+                value,
+                deck,
+                bonus: None,
+                ..*self
+            };
+            (tile, p * (1.0 - p_bonus))
+        });
+        // previewed as any bonus tile, where the preview window lands doesn't change the odds
+        let bonus = bonuses.iter().map(|value| {
+            let tile = Tile {
+                value: *value,
+                bonus: bonuses.first().zip(bonuses.last()).map(|(l, h)| (*l, *h)),
+                ..*self
+            };
+            (tile, p_bonus / bonuses.len() as f64)
+        });
+        cards.chain(bonus).collect()
+    }
+
+    // the bonus tiles which can be drawn, none below the threshold
+    fn bonus_values(&self, max: u32) -> Vec<u32> {
+        if max < BONUS_THRESHOLD {
+            return Vec::new();
+        }
+        (self.series.n(BONUS_MIN)..=self.series.n(max / 8))
+            .map(|rank| self.series.u_n(rank))
+            .collect()
+    }
+
+    // every tile next can return, with its probability
+    pub fn outcomes(&self, max: u32) -> Vec<(Tile, f64)> {
+        let max_rank = self.series.n(max);
+        // a third for 1, a third for 2 and a third for the rank
+        let mut ranks = vec![1.0 / 3.0, 1.0 / 3.0];
+        match max_rank {
+            0 | 1 => {
+                ranks[0] += 1.0 / 6.0;
+                ranks[1] += 1.0 / 6.0;
+            }
+            _ => {
+                let mut remaining = 1.0;
+                for rank in 0..max_rank {
+                    // ranks are 0-based, the distribution is 1 based
+                    let p = self.distribution.probability(rank + 1);
+                    remaining -= p;
+                    Self::add_rank(&mut ranks, rank, p / 3.0);
+                }
+                // everything above max_rank is capped to it
+                Self::add_rank(&mut ranks, max_rank, remaining / 3.0);
+            }
+        }
+        ranks
+            .into_iter()
+            .enumerate()
+            .filter(|(_, p)| *p > 0.0)
+            .map(|(rank, p)| {
+                let tile = Tile {
+                    value: self.series.u_n(rank as u32),
+                    ..*self
+                };
+                (tile, p)
+            })
+            .collect()
+    }
+
+    fn add_rank(ranks: &mut Vec<f64>, rank: u32, p: f64) {
+        let rank = rank as usize;
+        if ranks.len() <= rank {
+            ranks.resize(rank + 1, 0.0);
+        }
+        ranks[rank] += p;
+    }
+
     fn rank<R: Rng + ?Sized>(&self, r: &mut R, max: u32) -> u32 {
         let max_rank = self.series.n(max);
         match max_rank {
@@ -52,13 +220,13 @@ impl Tile {
 
 #[cfg(test)]
 mod tests {
-    use rand::rngs::OsRng;
+    use crate::rng;
 
     use super::*;
 
     #[test]
     fn next_is_less_than_or_equal_to_max() -> () {
-        let mut r = OsRng;
+        let mut r = rng::from_seed(42);
         let s = Tile::new(&mut r);
         let mut vec = Vec::new();
         let max = 12;
@@ -69,9 +237,162 @@ mod tests {
         assert!(vec.into_iter().all(|r| r <= 12));
     }
 
+    #[test]
+    fn outcomes_sum_to_1() {
+        let s = Tile::from_value(1);
+        for max in [1, 2, 3, 12, 384] {
+            let total: f64 = s.outcomes(max).iter().map(|(_, p)| p).sum();
+            assert!((total - 1.0).abs() < 1e-9);
+        }
+    }
+
+    #[test]
+    fn outcomes_are_at_most_max() {
+        let s = Tile::from_value(1);
+        let values: Vec<u32> = s.outcomes(12).iter().map(|(t, _)| t.current()).collect();
+        assert_eq!(values, vec![1, 2, 3, 6, 12]);
+    }
+
+    #[test]
+    fn outcomes_match_next() {
+        let mut r = rng::from_seed(42);
+        let s = Tile::from_value(1);
+        let samples = 30000;
+        let mut counts = std::collections::HashMap::new();
+        for _ in 0..samples {
+            *counts.entry(s.next(&mut r, 48).current()).or_insert(0) += 1;
+        }
+        for (t, p) in s.outcomes(48) {
+            let freq = counts.get(&t.current()).copied().unwrap_or(0) as f64 / samples as f64;
+            assert!(
+                (freq - p).abs() < 0.01,
+                "{}: {} vs {}",
+                t.current(),
+                freq,
+                p
+            );
+        }
+    }
+
+    fn draw_frequencies(max: u32, samples: usize) -> std::collections::HashMap<u32, usize> {
+        let mut r = rng::from_seed(42);
+        let mut t = Tile::from_deck(&mut r);
+        let mut counts = std::collections::HashMap::new();
+        for _ in 0..samples {
+            t = t.draw(&mut r, max);
+            *counts.entry(t.current()).or_insert(0) += 1;
+        }
+        counts
+    }
+
+    #[test]
+    fn draw_deals_whole_decks_below_48() {
+        let mut r = rng::from_seed(42);
+        let mut t = Tile::from_deck(&mut r);
+        let mut dealt = vec![t.current()];
+        for _ in 0..35 {
+            t = t.draw(&mut r, 24);
+            dealt.push(t.current());
+        }
+        // every run of 12 draws holds four of each card
+        for deck in dealt.chunks(12) {
+            for card in 1..=3 {
+                assert_eq!(deck.iter().filter(|c| **c == card).count(), 4);
+            }
+        }
+    }
+
+    #[test]
+    fn draw_is_a_third_of_each_card_below_48() {
+        let samples = 30000;
+        let counts = draw_frequencies(24, samples);
+        assert_eq!(counts.len(), 3);
+        for card in 1..=3 {
+            let freq = counts[&card] as f64 / samples as f64;
+            assert!((freq - 1.0 / 3.0).abs() < 0.01, "{}: {}", card, freq);
+        }
+    }
+
+    #[test]
+    fn draw_adds_one_bonus_tile_in_21_from_48() {
+        let samples = 60000;
+        let counts = draw_frequencies(384, samples);
+        let bonus: usize = counts.iter().filter(|(v, _)| **v > 3).map(|(_, c)| c).sum();
+        let freq = bonus as f64 / samples as f64;
+        assert!((freq - 1.0 / 21.0).abs() < 0.005, "{}", freq);
+        // bonus tiles go from 6 to an eighth of the highest tile
+        let mut values: Vec<u32> = counts.keys().copied().filter(|v| *v > 3).collect();
+        values.sort();
+        assert_eq!(values, vec![6, 12, 24, 48]);
+        for v in values {
+            let freq = counts[&v] as f64 / samples as f64;
+            assert!((freq - 1.0 / 84.0).abs() < 0.003, "{}: {}", v, freq);
+        }
+    }
+
+    #[test]
+    fn bonus_tiles_are_previewed_among_neighbours() {
+        let mut r = rng::from_seed(42);
+        let mut t = Tile::from_deck(&mut r);
+        let mut bonuses = 0;
+        for _ in 0..2000 {
+            t = t.draw(&mut r, 768);
+            let preview = t.preview();
+            if t.is_bonus() {
+                bonuses += 1;
+                assert_eq!(preview.len(), 3);
+                assert!(preview.contains(&t.current()));
+                assert!(preview.windows(2).all(|w| w[1] == w[0] * 2));
+                assert!(preview[0] >= 6 && preview[2] <= 96);
+            } else {
+                assert_eq!(preview, vec![t.current()]);
+            }
+        }
+        assert!(bonuses > 0);
+    }
+
+    #[test]
+    fn bonus_previews_shrink_with_the_candidates() {
+        // 6 is the only bonus tile at 48
+        let mut r = rng::from_seed(42);
+        let mut t = Tile::from_deck(&mut r);
+        while !t.is_bonus() {
+            t = t.draw(&mut r, 48);
+        }
+        assert_eq!(t.preview(), vec![6]);
+    }
+
+    #[test]
+    fn draw_outcomes_sum_to_1() {
+        let mut r = rng::from_seed(42);
+        let t = Tile::from_deck(&mut r);
+        for max in [3, 48, 768] {
+            let total: f64 = t.draw_outcomes(max).iter().map(|(_, p)| p).sum();
+            assert!((total - 1.0).abs() < 1e-9);
+        }
+    }
+
+    #[test]
+    fn draw_outcomes_match_draw() {
+        let samples = 60000;
+        let counts = draw_frequencies(96, samples);
+        // over many decks, each card is as likely as in a full one
+        let t = Tile::from_value(1);
+        for (tile, p) in t.draw_outcomes(96) {
+            let freq = counts[&tile.current()] as f64 / samples as f64;
+            assert!(
+                (freq - p).abs() < 0.01,
+                "{}: {} vs {}",
+                tile.current(),
+                freq,
+                p
+            );
+        }
+    }
+
     #[test]
     fn rank_0_or_1_if_max_1() -> () {
-        let mut r = OsRng;
+        let mut r = rng::from_seed(42);
         let s = Tile::new(&mut r);
         let mut vec = Vec::new();
         for _ in 0..=10 {
@@ -84,7 +405,7 @@ mod tests {
 
     #[test]
     fn rank_0_or_1_if_max_2() -> () {
-        let mut r = OsRng;
+        let mut r = rng::from_seed(42);
         let s = Tile::new(&mut r);
         let mut vec = Vec::new();
         for _ in 0..=10 {
@@ -97,7 +418,7 @@ mod tests {
 
     #[test]
     fn rank_possibly_2_if_max_3() -> () {
-        let mut r = OsRng;
+        let mut r = rng::from_seed(42);
         let s = Tile::new(&mut r);
         let mut vec = Vec::new();
         for _ in 0..=10 {
@@ -109,7 +430,7 @@ mod tests {
 
     #[test]
     fn rank_cant_have_more_than_max_rank() -> () {
-        let mut r = OsRng;
+        let mut r = rng::from_seed(42);
         let s = Tile::new(&mut r);
         let mut vec = Vec::new();
         for _ in 0..=1000 {
@@ -123,7 +444,7 @@ mod tests {
     #[test]
     fn rank_can_be_0_if_max_greater_than_2() -> () {
         // the distribution is 1-based, our ranks are 0-based
-        let mut r = OsRng;
+        let mut r = rng::from_seed(42);
         let s = Tile::new(&mut r);
         let mut vec = Vec::new();
         for _ in 0..=10 {
@@ -0,0 +1,320 @@
+// positions are written row by row from the top, rows separated by `/` and cells by `,`, empty
+// cells as `-`, followed by the next tile: `3,1,-,-/-,2,-,-/-,-,-,-/-,-,-,6 2`, or by the
+// candidates of a bonus tile: `... 6/12/24`, which tiles are valid depends on the rules
+// moves are written as their initials: `ULLDR`
+use std::{fmt, str::FromStr};
+
+use crate::{
+    model::{direction::Direction, grid::Grid, size::Size, tile::Tile},
+    rng,
+    rules::RuleSet,
+    state::State,
+};
+use anyhow::{anyhow, bail, Context, Error, Result};
+
+const EMPTY: &str = "-";
+const CELL_SEP: char = ',';
+const ROW_SEP: char = '/';
+// between the values a bonus tile is previewed as
+const CANDIDATE_SEP: char = '/';
+
+// 1, 2 or 3 * 2^n in threes, 2^n in 2048
+fn valid_tile(rules: RuleSet, value: u32) -> bool {
+    let rules = rules.rules();
+    value != 0 && rules.value(rules.rank(value)) == value
+}
+
+impl fmt::Display for Grid {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        for (i, row) in self.matrix.row_iter().enumerate() {
+            if i > 0 {
+                write!(f, "{}", ROW_SEP)?;
+            }
+            for (j, e) in row.iter().enumerate() {
+                if j > 0 {
+                    write!(f, "{}", CELL_SEP)?;
+                }
+                match e {
+                    0 => write!(f, "{}", EMPTY)?,
+                    v => write!(f, "{}", v)?,
+                }
+            }
+        }
+        Ok(())
+    }
+}
+
+// a grid of threes tiles
+impl FromStr for Grid {
+    type Err = Error;
+
+    fn from_str(s: &str) -> Result<Grid> {
+        Grid::parse(s, RuleSet::default())
+    }
+}
+
+impl Grid {
+    pub fn parse(s: &str, rules: RuleSet) -> Result<Grid> {
+        let rows: Vec<Vec<&str>> = s
+            .trim()
+            .split(ROW_SEP)
+            .map(|row| row.split(CELL_SEP).map(str::trim).collect())
+            .collect();
+        let cols = rows[0].len();
+        let size = Size::new(cols, rows.len())?;
+        let mut grid = Grid::empty(size);
+        for (i, cells) in rows.into_iter().enumerate() {
+            if cells.len() != cols {
+                bail!(
+                    "expected {} cells in row {}, found {}",
+                    cols,
+                    i + 1,
+                    cells.len()
+                );
+            }
+            for (j, cell) in cells.into_iter().enumerate() {
+                grid.matrix[(i, j)] = parse_tile(cell, rules)
+                    .with_context(|| format!("invalid cell at row {}, column {}", i + 1, j + 1))?;
+            }
+        }
+        Ok(grid)
+    }
+}
+
+fn parse_tile(s: &str, rules: RuleSet) -> Result<u32> {
+    if s == EMPTY {
+        return Ok(0);
+    }
+    let value = s
+        .parse::<u32>()
+        .map_err(|_| anyhow!("expected a tile or {}, found `{}`", EMPTY, s))?;
+    if !valid_tile(rules, value) {
+        bail!("{} is not a tile", value);
+    }
+    Ok(value)
+}
+
+impl fmt::Display for Direction {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let c = match self {
+            Direction::Up => 'U',
+            Direction::Down => 'D',
+            Direction::Left => 'L',
+            Direction::Right => 'R',
+        };
+        write!(f, "{}", c)
+    }
+}
+
+impl FromStr for Direction {
+    type Err = Error;
+
+    fn from_str(s: &str) -> Result<Direction> {
+        match s.trim() {
+            "U" | "u" => Ok(Direction::Up),
+            "D" | "d" => Ok(Direction::Down),
+            "L" | "l" => Ok(Direction::Left),
+            "R" | "r" => Ok(Direction::Right),
+            other => bail!("expected one of U, D, L or R, found `{}`", other),
+        }
+    }
+}
+
+pub fn format_moves(moves: &[Direction]) -> String {
+    moves.iter().map(|d| d.to_string()).collect()
+}
+
+// whitespace between moves is ignored
+pub fn parse_moves(s: &str) -> Result<Vec<Direction>> {
+    s.chars()
+        .filter(|c| !c.is_whitespace())
+        .enumerate()
+        .map(|(i, c)| {
+            c.to_string()
+                .parse()
+                .with_context(|| format!("invalid move {}", i + 1))
+        })
+        .collect()
+}
+
+impl State {
+    // bonus tiles are written as what they are previewed as, not to give them away
+    pub fn notation(&self) -> String {
+        let candidates: Vec<String> = self.tile().preview().iter().map(u32::to_string).collect();
+        format!(
+            "{} {}",
+            self.grid(),
+            candidates.join(&CANDIDATE_SEP.to_string())
+        )
+    }
+
+    // the seed drives the tiles spawned from this position on
+    pub fn from_notation(s: &str, seed: u64, rules: RuleSet) -> Result<State> {
+        let (grid, next) = s
+            .trim()
+            .rsplit_once(char::is_whitespace)
+            .context("expected a grid followed by the next tile")?;
+        let grid = Grid::parse(grid, rules)?;
+        let candidates = next
+            .trim()
+            .split(CANDIDATE_SEP)
+            .map(|s| parse_tile(s.trim(), rules))
+            .collect::<Result<Vec<_>>>()
+            .context("invalid next tile")?;
+        if candidates.contains(&0) {
+            bail!("the next tile can't be empty");
+        }
+        if !candidates
+            .windows(2)
+            .all(|w| w[0].checked_mul(2) == Some(w[1]))
+        {
+            bail!(
+                "invalid next tile: expected candidates doubling from one to the next, found `{}`",
+                next.trim()
+            );
+        }
+        let mut r = rng::from_seed(seed);
+        let tile = Tile::from_preview(&mut r, &candidates);
+        Ok(State::from_parts(seed, rules, r, grid, tile))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use nalgebra::Matrix4;
+
+    use super::*;
+
+    #[test]
+    fn grid_round_trips() {
+        let m = Matrix4::new(2, 3, 1, 3, 3, 1, 3, 2, 6, 3, 24, 2, 3, 48, 192, 0);
+        let g = Grid::from(m);
+        let s = g.to_string();
+        assert_eq!(s, "2,3,1,3/3,1,3,2/6,3,24,2/3,48,192,-");
+        assert_eq!(s.parse::<Grid>().unwrap(), g);
+    }
+
+    #[test]
+    fn grid_parse_tolerates_spaces() {
+        let g: Grid = " 3, 1, -, - / -, 2, -, - / -, -, -, - / -, -, -, 6 "
+            .parse()
+            .unwrap();
+        assert_eq!(g.matrix[(0, 0)], 3);
+        assert_eq!(g.matrix[(3, 3)], 6);
+    }
+
+    #[test]
+    fn grid_parse_reads_any_size() {
+        let g: Grid = "3,-,-/-,-,6".parse().unwrap();
+        assert_eq!(g.size(), Size { cols: 3, rows: 2 });
+        assert_eq!(g.matrix[(1, 2)], 6);
+        assert_eq!(g.to_string(), "3,-,-/-,-,6");
+    }
+
+    #[test]
+    fn grid_parse_rejects_wrong_shapes() {
+        let err = "-,-,-,-".parse::<Grid>().unwrap_err();
+        assert_eq!(err.to_string(), "expected between 2 and 8 rows, found 1");
+        let err = "-,-,-,-/-,-,-/-,-,-,-/-,-,-,-".parse::<Grid>().unwrap_err();
+        assert_eq!(err.to_string(), "expected 4 cells in row 2, found 3");
+    }
+
+    #[test]
+    fn grid_parse_rejects_invalid_tiles() {
+        let err = "-,-,-,-/-,-,5,-/-,-,-,-/-,-,-,-"
+            .parse::<Grid>()
+            .unwrap_err();
+        assert_eq!(
+            format!("{:#}", err),
+            "invalid cell at row 2, column 3: 5 is not a tile"
+        );
+        assert!("-,-,-,-/-,-,x,-/-,-,-,-/-,-,-,-".parse::<Grid>().is_err());
+        assert!("-,-,-,-/-,-,0,-/-,-,-,-/-,-,-,-".parse::<Grid>().is_err());
+    }
+
+    #[test]
+    fn moves_round_trip() {
+        let moves = parse_moves("UD lr").unwrap();
+        let expected = vec![
+            Direction::Up,
+            Direction::Down,
+            Direction::Left,
+            Direction::Right,
+        ];
+        assert_eq!(moves, expected);
+        assert_eq!(format_moves(&moves), "UDLR");
+    }
+
+    #[test]
+    fn parse_moves_reports_the_invalid_move() {
+        let err = parse_moves("UDX").unwrap_err();
+        assert_eq!(
+            format!("{:#}", err),
+            "invalid move 3: expected one of U, D, L or R, found `X`"
+        );
+    }
+
+    #[test]
+    fn state_round_trips() {
+        let s = State::new(7);
+        let parsed = State::from_notation(&s.notation(), 7, RuleSet::Threes).unwrap();
+        assert_eq!(parsed.grid(), s.grid());
+        assert_eq!(parsed.next_tile(), s.next_tile());
+    }
+
+    #[test]
+    fn state_from_notation_is_playable() {
+        let mut s =
+            State::from_notation("1,2,-,-/-,-,-,-/-,-,-,-/-,-,-,- 3", 7, RuleSet::Threes).unwrap();
+        s.shift(Direction::Left);
+        assert_eq!(s.grid().matrix[(0, 0)], 3);
+        assert_eq!(s.grid().matrix[(0, 3)], 3);
+    }
+
+    #[test]
+    fn state_round_trips_with_other_rules() {
+        let mut s = State::with_rules(7, Size::default(), RuleSet::TwentyFortyEight);
+        s.shift(s.legal_moves()[0]);
+        let parsed = State::from_notation(&s.notation(), 7, RuleSet::TwentyFortyEight).unwrap();
+        assert_eq!(parsed.grid(), s.grid());
+        assert_eq!(parsed.next_tile(), s.next_tile());
+        assert_eq!(parsed.rules(), RuleSet::TwentyFortyEight);
+    }
+
+    #[test]
+    fn tiles_are_valid_as_the_rules_have_them() {
+        let position = "4,8,-,-/-,-,-,-/-,-,-,-/-,-,-,2048 2";
+        assert!(State::from_notation(position, 7, RuleSet::TwentyFortyEight).is_ok());
+        assert!(State::from_notation(position, 7, RuleSet::Threes).is_err());
+        let position = "3,6,-,-/-,-,-,-/-,-,-,-/-,-,-,- 1";
+        assert!(State::from_notation(position, 7, RuleSet::ThreesRandom).is_ok());
+        assert!(State::from_notation(position, 7, RuleSet::TwentyFortyEight).is_err());
+    }
+
+    #[test]
+    fn bonus_tiles_are_written_as_their_preview() {
+        let position = "192,48,-,-/-,-,-,-/-,-,-,-/-,-,-,- 6/12/24";
+        let s = State::from_notation(position, 7, RuleSet::Threes).unwrap();
+        assert!(s.tile().is_bonus());
+        assert_eq!(s.tile().preview(), vec![6, 12, 24]);
+        assert!(s.tile().preview().contains(&s.next_tile()));
+        assert_eq!(s.notation(), position);
+        let err = State::from_notation("-,-/-,- 6/24", 7, RuleSet::Threes)
+            .err()
+            .unwrap();
+        assert_eq!(
+            err.to_string(),
+            "invalid next tile: expected candidates doubling from one to the next, found `6/24`"
+        );
+    }
+
+    #[test]
+    fn state_from_notation_requires_a_next_tile() {
+        assert!(
+            State::from_notation("-,-,-,-/-,-,-,-/-,-,-,-/-,-,-,-", 7, RuleSet::Threes).is_err()
+        );
+        assert!(
+            State::from_notation("-,-,-,-/-,-,-,-/-,-,-,-/-,-,-,- -", 7, RuleSet::Threes).is_err()
+        );
+    }
+}
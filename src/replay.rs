@@ -0,0 +1,217 @@
+use std::{
+    fs,
+    path::{Path, PathBuf},
+};
+
+use anyhow::{bail, Context, Result};
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    model::{direction::Direction, grid::Grid, tile::Tile},
+    rng::GameRng,
+    rules::RuleSet,
+    state::State,
+};
+
+// to be bumped whenever the serialized Replay changes in an incompatible way
+pub const VERSION: u32 = 1;
+
+#[derive(Debug, PartialEq, Eq, Clone, Serialize, Deserialize)]
+pub struct Step {
+    pub direction: Direction,
+    // value of the tile inserted by the move, if any
+    pub spawned: Option<u32>,
+}
+
+// a game as its starting position followed by its moves, the rng makes spawns reproducible
+#[derive(Clone, Serialize, Deserialize)]
+pub struct Replay {
+    version: u32,
+    seed: u64,
+    rules: RuleSet,
+    grid: Grid,
+    tile: Tile,
+    rng: GameRng,
+    steps: Vec<Step>,
+}
+
+// read first to report version mismatches rather than a cryptic deserialization error
+#[derive(Deserialize)]
+struct Header {
+    version: u32,
+}
+
+impl Replay {
+    pub(crate) fn new(
+        seed: u64,
+        rules: RuleSet,
+        grid: Grid,
+        tile: Tile,
+        rng: GameRng,
+        steps: Vec<Step>,
+    ) -> Replay {
+        Replay {
+            version: VERSION,
+            seed,
+            rules,
+            grid,
+            tile,
+            rng,
+            steps,
+        }
+    }
+
+    pub fn seed(&self) -> u64 {
+        self.seed
+    }
+
+    pub fn rules(&self) -> RuleSet {
+        self.rules
+    }
+
+    pub fn steps(&self) -> &[Step] {
+        &self.steps
+    }
+
+    // every state of the game, from the starting position to the one after the last step
+    pub fn states(&self) -> Result<Vec<State>> {
+        let mut state = State::from_parts(
+            self.seed,
+            self.rules,
+            self.rng.clone(),
+            self.grid.clone(),
+            self.tile,
+        );
+        let mut states = Vec::with_capacity(self.steps.len() + 1);
+        states.push(state.clone());
+        for (i, step) in self.steps.iter().enumerate() {
+            state.shift(step.direction);
+            if state.last_step() != Some(step) {
+                bail!("replay diverges at move {}", i + 1);
+            }
+            states.push(state.clone());
+        }
+        Ok(states)
+    }
+}
+
+// $XDG_DATA_HOME/threes/replays on linux
+pub fn default_dir() -> Result<PathBuf> {
+    dirs::data_dir()
+        .map(|d| d.join("threes").join("replays"))
+        .context("no data directory found to store replays in")
+}
+
+pub fn to_string(replay: &Replay) -> Result<String> {
+    serde_json::to_string(replay).context("failed to serialize the replay")
+}
+
+pub fn from_str(s: &str) -> Result<Replay> {
+    let header: Header =
+        serde_json::from_str(s).context("replay is corrupt, it has no format version")?;
+    if header.version != VERSION {
+        bail!(
+            "replay was written with format version {} but this version of threes reads version {}",
+            header.version,
+            VERSION
+        );
+    }
+    serde_json::from_str(s).context("replay is corrupt")
+}
+
+pub fn write(path: &Path, replay: &Replay) -> Result<()> {
+    if let Some(dir) = path.parent() {
+        fs::create_dir_all(dir).with_context(|| format!("failed to create {}", dir.display()))?;
+    }
+    fs::write(path, to_string(replay)?)
+        .with_context(|| format!("failed to write {}", path.display()))
+}
+
+pub fn read(path: &Path) -> Result<Replay> {
+    let s =
+        fs::read_to_string(path).with_context(|| format!("failed to read {}", path.display()))?;
+    from_str(&s).with_context(|| format!("failed to load {}", path.display()))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn played() -> State {
+        let mut s = State::new(7);
+        for d in [
+            Direction::Left,
+            Direction::Up,
+            Direction::Right,
+            Direction::Down,
+        ]
+        .repeat(5)
+        {
+            s.shift(d);
+        }
+        s
+    }
+
+    #[test]
+    fn states_replay_the_game() {
+        let s = played();
+        let states = s.replay().states().unwrap();
+        assert_eq!(states.len(), s.moves() + 1);
+        assert_eq!(states[0].grid(), State::new(7).grid());
+        let last = states.last().unwrap();
+        assert_eq!(last.grid(), s.grid());
+        assert_eq!(last.next_tile(), s.next_tile());
+    }
+
+    #[test]
+    fn replay_records_spawned_tiles() {
+        let mut s = State::new(7);
+        let next = s.next_tile();
+        s.shift(Direction::Left);
+        let expected = Step {
+            direction: Direction::Left,
+            spawned: Some(next),
+        };
+        assert_eq!(s.replay().steps(), &[expected]);
+    }
+
+    #[test]
+    fn undone_moves_are_not_replayed() {
+        let mut s = played();
+        let moves = s.moves();
+        s.undo();
+        s.undo();
+        assert_eq!(s.replay().steps().len(), moves - 2);
+        s.redo();
+        assert_eq!(s.replay().steps().len(), moves - 1);
+        let states = s.replay().states().unwrap();
+        assert_eq!(states.last().unwrap().grid(), s.grid());
+    }
+
+    #[test]
+    fn states_fail_if_steps_diverge() {
+        let mut r = played().replay();
+        let spawned = r.steps[0].spawned.unwrap();
+        r.steps[0].spawned = Some(spawned + 1);
+        let err = r.states().err().unwrap();
+        assert_eq!(err.to_string(), "replay diverges at move 1");
+    }
+
+    #[test]
+    fn round_trip_preserves_the_replay() {
+        let r = played().replay();
+        let loaded = from_str(&to_string(&r).unwrap()).unwrap();
+        assert_eq!(loaded.seed(), r.seed());
+        assert_eq!(loaded.steps(), r.steps());
+    }
+
+    #[test]
+    fn from_str_rejects_other_versions() {
+        let s = to_string(&played().replay()).unwrap().replacen(
+            &format!("\"version\":{}", VERSION),
+            "\"version\":999",
+            1,
+        );
+        assert!(from_str(&s).is_err());
+    }
+}
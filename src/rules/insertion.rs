@@ -0,0 +1,113 @@
+use crate::model::grid::Slide;
+
+// where the next tile can spawn once the lines moved, it spawns in one of the cells uniformly
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum Insertion {
+    // as the original game, at the trailing end of any line which moved
+    #[default]
+    MovedLine,
+    // at the end of the first line which combined, otherwise in any free cell of the trailing edge
+    FirstCombined,
+    // in any free cell
+    AnyFreeCell,
+}
+
+impl Insertion {
+    pub fn cells(self, slide: &Slide) -> Vec<(usize, usize)> {
+        match self {
+            Insertion::MovedLine => slide
+                .moved
+                .iter()
+                .map(|line| slide.trailing_cell(*line))
+                .filter(|c| slide.grid.matrix[*c] == 0)
+                .collect(),
+            Insertion::FirstCombined => match slide.combined.first() {
+                Some(line) => vec![slide.trailing_cell(*line)],
+                None => slide.empty_edge_cells(),
+            },
+            Insertion::AnyFreeCell => {
+                let m = &slide.grid.matrix;
+                // row by row, to match how the board reads
+                (0..m.nrows())
+                    .flat_map(|i| (0..m.ncols()).map(move |j| (i, j)))
+                    .filter(|c| m[*c] == 0)
+                    .collect()
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use nalgebra::Matrix4;
+
+    use crate::{
+        model::{direction::Direction, grid::Grid},
+        rules::threes::Threes,
+    };
+
+    use super::*;
+
+    // the first row slides, the second is stuck, the third and fourth combine and the last
+    // one has a free trailing cell but doesn't move
+    fn position() -> Grid {
+        Grid::from(Matrix4::new(
+            0, 3, 0, 0, 3, 6, 12, 24, 1, 2, 3, 6, 3, 3, 6, 12,
+        ))
+    }
+
+    fn slide(grid: &Grid, direction: Direction) -> Slide {
+        grid.slide(&Threes::default(), direction)
+    }
+
+    #[test]
+    fn moved_line_picks_the_end_of_any_moved_line() {
+        let s = slide(&position(), Direction::Left);
+        assert_eq!(s.moved, vec![0, 2, 3]);
+        assert_eq!(Insertion::MovedLine.cells(&s), vec![(0, 3), (2, 3), (3, 3)]);
+    }
+
+    #[test]
+    fn moved_line_ignores_lines_which_did_not_move() {
+        let g = Grid::from(Matrix4::new(0, 3, 0, 0, 3, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0));
+        let s = slide(&g, Direction::Left);
+        // the second row has free cells at its end but stays put
+        assert_eq!(s.moved, vec![0]);
+        assert_eq!(Insertion::MovedLine.cells(&s), vec![(0, 3)]);
+        assert_eq!(
+            Insertion::FirstCombined.cells(&s),
+            vec![(0, 3), (1, 3), (2, 3), (3, 3)]
+        );
+    }
+
+    #[test]
+    fn moved_line_works_along_columns() {
+        let g = Grid::from(Matrix4::new(0, 3, 3, 0, 3, 6, 3, 0, 0, 0, 0, 0, 0, 0, 0, 0));
+        let s = slide(&g, Direction::Down);
+        assert_eq!(s.moved, vec![0, 1, 2]);
+        assert_eq!(Insertion::MovedLine.cells(&s), vec![(0, 0), (0, 1), (0, 2)]);
+    }
+
+    #[test]
+    fn first_combined_picks_the_first_combined_line() {
+        let s = slide(&position(), Direction::Left);
+        assert_eq!(Insertion::FirstCombined.cells(&s), vec![(2, 3)]);
+    }
+
+    #[test]
+    fn any_free_cell_picks_every_empty_cell() {
+        let s = slide(&position(), Direction::Left);
+        assert_eq!(
+            Insertion::AnyFreeCell.cells(&s),
+            vec![(0, 1), (0, 2), (0, 3), (2, 3), (3, 3)]
+        );
+    }
+
+    #[test]
+    fn spawns_are_uniform_over_the_cells() {
+        let rules = Threes::default();
+        let outcomes = position().shift_outcomes(&rules, Direction::Left, 2);
+        assert_eq!(outcomes.len(), 3);
+        assert!(outcomes.iter().all(|(_, p)| (p - 1.0 / 3.0).abs() < 1e-9));
+    }
+}
@@ -0,0 +1,157 @@
+use std::{fmt, str::FromStr};
+
+use anyhow::{bail, Error, Result};
+use rand::RngCore;
+use serde::{Deserialize, Serialize};
+
+use crate::model::{
+    grid::{Grid, Slide},
+    size::Size,
+    tile::Tile,
+};
+
+use self::{
+    insertion::Insertion,
+    threes::{Generator, Threes},
+    twenty_forty_eight::TwentyFortyEight,
+};
+
+pub mod insertion;
+pub mod threes;
+pub mod twenty_forty_eight;
+
+// everything which differs between games played by sliding tiles on a grid
+pub trait Rules {
+    // what two adjacent tiles combine into, if they can
+    fn combine(&self, a: u32, b: u32) -> Option<u32>;
+
+    // shifts a line towards its first cell, returns the new line, whether it moved and whether
+    // it combined
+    fn shift_line(&self, line: &[u32]) -> (Vec<u32>, bool, bool);
+
+    // for every cell of the shifted line, the cells of the line whose tiles moved into it, two of
+    // them if they combined
+    fn line_sources(&self, line: &[u32]) -> Vec<Vec<usize>>;
+
+    // where the next tile spawns once the lines moved
+    fn insertion(&self) -> Insertion;
+
+    // the cells the next tile can spawn in once the lines moved, it spawns in one of them
+    // uniformly, none if it can't spawn
+    fn spawn_cells(&self, slide: &Slide) -> Vec<(usize, usize)> {
+        self.insertion().cells(slide)
+    }
+
+    // what a tile is worth on the board at the end of the game
+    fn tile_score(&self, value: u32) -> u64;
+
+    // how far a tile is from the smallest one, 0 for empty cells
+    fn rank(&self, value: u32) -> u32;
+
+    // reciprocal of rank
+    fn value(&self, rank: u32) -> u32;
+
+    fn initial_grid(&self, r: &mut dyn RngCore, size: Size) -> Grid;
+
+    fn first_tile(&self, r: &mut dyn RngCore) -> Tile;
+
+    // the tile spawning after this one, max being the highest tile on the board
+    fn next_tile(&self, r: &mut dyn RngCore, tile: Tile, max: u32) -> Tile;
+
+    // every tile next_tile can return, with its probability
+    fn tile_outcomes(&self, tile: Tile, max: u32) -> Vec<(Tile, f64)>;
+}
+
+// the rules a game is played with, kept in saves and replays
+#[derive(Debug, PartialEq, Eq, Copy, Clone, Hash, Default, Serialize, Deserialize)]
+pub enum RuleSet {
+    #[default]
+    Threes,
+    // threes as first implemented here, with the next tiles picked at random rather than from a
+    // deck and spawning at the end of the first line which combined
+    ThreesRandom,
+    TwentyFortyEight,
+}
+
+impl RuleSet {
+    pub const ALL: [RuleSet; 3] = [
+        RuleSet::Threes,
+        RuleSet::ThreesRandom,
+        RuleSet::TwentyFortyEight,
+    ];
+
+    pub fn rules(self) -> &'static dyn Rules {
+        match self {
+            RuleSet::Threes => &Threes {
+                generator: Generator::Deck,
+                insertion: Insertion::MovedLine,
+            },
+            RuleSet::ThreesRandom => &Threes {
+                generator: Generator::Random,
+                insertion: Insertion::FirstCombined,
+            },
+            RuleSet::TwentyFortyEight => &TwentyFortyEight,
+        }
+    }
+}
+
+impl fmt::Display for RuleSet {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            RuleSet::Threes => write!(f, "threes"),
+            RuleSet::ThreesRandom => write!(f, "threes-random"),
+            RuleSet::TwentyFortyEight => write!(f, "2048"),
+        }
+    }
+}
+
+impl FromStr for RuleSet {
+    type Err = Error;
+
+    fn from_str(s: &str) -> Result<RuleSet> {
+        match RuleSet::ALL.into_iter().find(|r| r.to_string() == s.trim()) {
+            Some(rules) => Ok(rules),
+            None => bail!("expected threes, threes-random or 2048, found `{}`", s),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // every line of 4 cells holding one of the 5 lowest tiles
+    fn lines(rules: &dyn Rules) -> Vec<Vec<u32>> {
+        (0..6_u32.pow(4))
+            .map(|n| (0..4).map(|i| rules.value(n / 6_u32.pow(i) % 6)).collect())
+            .collect()
+    }
+
+    #[test]
+    fn line_sources_agree_with_shift_line() {
+        for rules in RuleSet::ALL {
+            let rules = rules.rules();
+            for line in lines(rules) {
+                let shifted: Vec<u32> = rules
+                    .line_sources(&line)
+                    .iter()
+                    .map(|sources| match sources[..] {
+                        [] => 0,
+                        [p] => line[p],
+                        [a, b] => rules.combine(line[a], line[b]).unwrap(),
+                        _ => panic!("more than two tiles in a cell of {:?}", line),
+                    })
+                    .collect();
+                assert_eq!(shifted, rules.shift_line(&line).0, "{:?}", line);
+            }
+        }
+    }
+
+    #[test]
+    fn rule_sets_round_trip() {
+        for rules in RuleSet::ALL {
+            assert_eq!(rules.to_string().parse::<RuleSet>().unwrap(), rules);
+        }
+        assert!("tetris".parse::<RuleSet>().is_err());
+    }
+}
@@ -0,0 +1,275 @@
+use rand::RngCore;
+
+use crate::{
+    math::series::Series,
+    model::{grid::Grid, size::Size, tile::Tile},
+};
+
+use super::{insertion::Insertion, Rules};
+
+// number of empty cells, 1s, 2s and 3s the initial 4x4 grid is built from
+pub const BASE_VALUES: [u32; 4] = [4, 2, 2, 2];
+
+// scales BASE_VALUES to the number of cells so that boards start as full as a 4x4 one
+pub fn base_values(size: Size) -> [u32; 4] {
+    let default = Size::default().cells() as u32;
+    BASE_VALUES.map(|v| v * size.cells() as u32 / default)
+}
+
+// how the next tiles are picked
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum Generator {
+    // as the original game, from a shuffled deck of four 1s, 2s and 3s with the odd bonus tile
+    #[default]
+    Deck,
+    // a third of 1s, a third of 2s and a third of tiles of geometrically distributed ranks
+    Random,
+}
+
+// 1s and 2s make 3s, tiles of at least 3 double, lines move one cell at a time
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub struct Threes {
+    pub generator: Generator,
+    pub insertion: Insertion,
+}
+
+const SERIES: Series = Series::new(1, 2, 2);
+
+impl Rules for Threes {
+    fn combine(&self, h1: u32, h2: u32) -> Option<u32> {
+        if h1 == h2 && h1 > 2 {
+            Some(h1 * 2)
+        } else if h1 + h2 == 3 && h1 < 3 && h2 < 3 {
+            Some(h1 + h2)
+        } else {
+            None
+        }
+    }
+
+    fn shift_line(&self, elements: &[u32]) -> (Vec<u32>, bool, bool) {
+        fn inner(
+            elements: &[u32],
+            mut acc: Vec<u32>,
+            mutated: bool,
+            combined: bool,
+            combiner: &dyn Fn(u32, u32) -> Option<u32>,
+        ) -> (Vec<u32>, bool, bool) {
+            if !combined {
+                match elements {
+                    [h1, h2, t @ ..] => {
+                        if let Some(value) = combiner(*h1, *h2) {
+                            acc.push(value);
+                            inner(t, acc, true, true, combiner)
+                        } else if h1 == &0 {
+                            acc.push(*h2);
+                            // todo: find a way to avoid the vec allocation
+                            let mut es: Vec<u32> = t.to_vec();
+                            es.insert(0, 0);
+                            inner(es.as_slice(), acc, true, combined, combiner)
+                        } else {
+                            acc.push(*h1);
+                            inner(&elements[1..], acc, mutated, combined, combiner)
+                        }
+                    }
+                    [h, t @ ..] => {
+                        acc.push(*h);
+                        inner(t, acc, mutated, combined, combiner)
+                    }
+                    _ => (acc, mutated, combined),
+                }
+            } else {
+                // todo: find a way to short-circuit
+                match elements {
+                    [h, t @ ..] => {
+                        acc.push(*h);
+                        inner(t, acc, mutated, combined, combiner)
+                    }
+                    _ => (acc, mutated, combined),
+                }
+            }
+        }
+
+        let (mut res, mutated, combined) = inner(
+            elements,
+            Vec::with_capacity(elements.len()),
+            false,
+            false,
+            &|h1, h2| self.combine(h1, h2),
+        );
+        if combined {
+            res.push(0);
+        }
+        (res, mutated, combined)
+    }
+
+    // tiles past the first empty cell or pair of tiles which combine move a cell
+    fn line_sources(&self, line: &[u32]) -> Vec<Vec<usize>> {
+        let first = (0..line.len().saturating_sub(1))
+            .find(|p| line[*p] == 0 || self.combine(line[*p], line[p + 1]).is_some());
+        let mut sources = vec![Vec::new(); line.len()];
+        for (p, value) in line.iter().enumerate() {
+            if *value == 0 {
+                continue;
+            }
+            match first {
+                Some(first) if p > first => sources[p - 1].push(p),
+                _ => sources[p].push(p),
+            }
+        }
+        sources
+    }
+
+    fn insertion(&self) -> Insertion {
+        self.insertion
+    }
+
+    // 3^(rank - 1) per tile of at least 3
+    fn tile_score(&self, value: u32) -> u64 {
+        if value < 3 {
+            0
+        } else {
+            (3_u64).pow(SERIES.n(value) - 1)
+        }
+    }
+
+    fn rank(&self, value: u32) -> u32 {
+        if value == 0 {
+            0
+        } else {
+            SERIES.n(value) + 1
+        }
+    }
+
+    fn value(&self, rank: u32) -> u32 {
+        if rank == 0 {
+            0
+        } else {
+            SERIES.u_n(rank - 1)
+        }
+    }
+
+    fn initial_grid(&self, r: &mut dyn RngCore, size: Size) -> Grid {
+        Grid::rand(r, base_values(size), size)
+    }
+
+    fn first_tile(&self, r: &mut dyn RngCore) -> Tile {
+        match self.generator {
+            Generator::Deck => Tile::from_deck(r),
+            Generator::Random => Tile::new(r),
+        }
+    }
+
+    fn next_tile(&self, r: &mut dyn RngCore, tile: Tile, max: u32) -> Tile {
+        match self.generator {
+            Generator::Deck => tile.draw(r, max),
+            Generator::Random => tile.next(r, max),
+        }
+    }
+
+    fn tile_outcomes(&self, tile: Tile, max: u32) -> Vec<(Tile, f64)> {
+        match self.generator {
+            Generator::Deck => tile.draw_outcomes(max),
+            Generator::Random => tile.outcomes(max),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn base_values_scale_with_the_board() {
+        assert_eq!(base_values(Size::default()), BASE_VALUES);
+        assert_eq!(base_values(Size { cols: 3, rows: 3 }), [2, 1, 1, 1]);
+        assert_eq!(base_values(Size { cols: 8, rows: 8 }), [16, 8, 8, 8]);
+    }
+
+    #[test]
+    fn combine_1_2_and_equal_tiles_of_at_least_3() {
+        assert_eq!(Threes::default().combine(1, 2), Some(3));
+        assert_eq!(Threes::default().combine(2, 1), Some(3));
+        assert_eq!(Threes::default().combine(6, 6), Some(12));
+        assert_eq!(Threes::default().combine(1, 1), None);
+        assert_eq!(Threes::default().combine(2, 2), None);
+        assert_eq!(Threes::default().combine(3, 6), None);
+    }
+
+    #[test]
+    fn value_is_the_reciprocal_of_rank() {
+        for value in [0, 1, 2, 3, 6, 12, 24, 768] {
+            assert_eq!(
+                Threes::default().value(Threes::default().rank(value)),
+                value
+            );
+        }
+    }
+
+    #[test]
+    fn tile_score_is_a_power_of_3() {
+        assert_eq!(Threes::default().tile_score(2), 0);
+        assert_eq!(Threes::default().tile_score(3), 3);
+        assert_eq!(Threes::default().tile_score(6), 9);
+        assert_eq!(Threes::default().tile_score(12), 27);
+    }
+
+    #[test]
+    fn shift_line_mutate_zeros() -> () {
+        let array = [1, 0, 2, 2];
+        let (res, mutated, combined) = Threes::default().shift_line(&array);
+        assert!(mutated);
+        assert!(!combined);
+        let expected = vec![1, 2, 2, 0];
+        assert_eq!(res, expected);
+    }
+
+    #[test]
+    fn shift_line_should_not_mutate_if_immutable() -> () {
+        let array = [3, 6, 9, 12];
+        let (res, mutated, combined) = Threes::default().shift_line(&array);
+        assert!(!mutated);
+        assert!(!combined);
+        let expected: Vec<u32> = array.into();
+        assert_eq!(res, expected);
+    }
+
+    #[test]
+    fn shift_line_should_combine_if_adjacent_are_same() -> () {
+        let array = [12, 12, 3, 6];
+        let (res, mutated, combined) = Threes::default().shift_line(&array);
+        assert!(mutated);
+        assert!(combined);
+        let expected = vec![24, 3, 6, 0];
+        assert_eq!(res, expected);
+    }
+
+    #[test]
+    fn shift_line_should_combine_only_once() -> () {
+        let array = [12, 12, 6, 6];
+        let (res, mutated, combined) = Threes::default().shift_line(&array);
+        assert!(mutated);
+        assert!(combined);
+        let expected = vec![24, 6, 6, 0];
+        assert_eq!(res, expected);
+    }
+
+    #[test]
+    fn shift_line_should_combine_1_2() -> () {
+        let array = [1, 2, 6, 6];
+        let (res, mutated, combined) = Threes::default().shift_line(&array);
+        assert!(mutated);
+        assert!(combined);
+        let expected = vec![3, 6, 6, 0];
+        assert_eq!(res, expected);
+    }
+
+    #[test]
+    fn shift_line_should_combine_2_1() -> () {
+        let array = [2, 1, 6, 6];
+        let (res, mutated, combined) = Threes::default().shift_line(&array);
+        assert!(mutated);
+        assert!(combined);
+        let expected = vec![3, 6, 6, 0];
+        assert_eq!(res, expected);
+    }
+}
@@ -0,0 +1,193 @@
+use rand::{distributions::Uniform, Rng, RngCore};
+
+use crate::{
+    model::{grid::Grid, size::Size, tile::Tile},
+    rng,
+};
+
+use super::{insertion::Insertion, Rules};
+
+// chances out of 10 for a 2 to spawn rather than a 4
+const TWO_IN_TEN: u32 = 9;
+const INITIAL_TILES: usize = 2;
+
+// equal tiles double, lines move as far as they can, tiles spawn in any free cell
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub struct TwentyFortyEight;
+
+impl TwentyFortyEight {
+    fn draw(r: &mut dyn RngCore) -> Tile {
+        let value = if r.sample(Uniform::new(0, 10)) < TWO_IN_TEN {
+            2
+        } else {
+            4
+        };
+        Tile::from_value(value)
+    }
+}
+
+impl Rules for TwentyFortyEight {
+    fn combine(&self, h1: u32, h2: u32) -> Option<u32> {
+        (h1 == h2 && h1 > 0).then_some(h1 * 2)
+    }
+
+    // every tile combines at most once per move
+    fn shift_line(&self, elements: &[u32]) -> (Vec<u32>, bool, bool) {
+        let mut res = Vec::with_capacity(elements.len());
+        let mut combined = false;
+        let mut tiles = elements.iter().copied().filter(|e| *e != 0).peekable();
+        while let Some(tile) = tiles.next() {
+            match tiles.peek().and_then(|next| self.combine(tile, *next)) {
+                Some(value) => {
+                    res.push(value);
+                    tiles.next();
+                    combined = true;
+                }
+                None => res.push(tile),
+            }
+        }
+        res.resize(elements.len(), 0);
+        let mutated = res != elements;
+        (res, mutated, combined)
+    }
+
+    // as shift_line, cell by cell
+    fn line_sources(&self, line: &[u32]) -> Vec<Vec<usize>> {
+        let mut sources = vec![Vec::new(); line.len()];
+        let mut tiles = line.iter().enumerate().filter(|(_, e)| **e != 0).peekable();
+        let mut cell = 0;
+        while let Some((p, tile)) = tiles.next() {
+            sources[cell].push(p);
+            if let Some((q, _)) = tiles.next_if(|(_, next)| self.combine(*tile, **next).is_some()) {
+                sources[cell].push(q);
+            }
+            cell += 1;
+        }
+        sources
+    }
+
+    fn insertion(&self) -> Insertion {
+        Insertion::AnyFreeCell
+    }
+
+    // every combination scores the tile it makes, 4 is the largest tile which can spawn
+    fn tile_score(&self, value: u32) -> u64 {
+        if value < 4 {
+            0
+        } else {
+            value as u64 * (value.ilog2() as u64 - 1)
+        }
+    }
+
+    fn rank(&self, value: u32) -> u32 {
+        if value == 0 {
+            0
+        } else {
+            value.ilog2()
+        }
+    }
+
+    fn value(&self, rank: u32) -> u32 {
+        if rank == 0 {
+            0
+        } else {
+            1 << rank
+        }
+    }
+
+    fn initial_grid(&self, r: &mut dyn RngCore, size: Size) -> Grid {
+        let mut grid = Grid::empty(size);
+        for _ in 0..INITIAL_TILES {
+            let free: Vec<(usize, usize)> = (0..size.rows)
+                .flat_map(|i| (0..size.cols).map(move |j| (i, j)))
+                .filter(|c| grid.matrix[*c] == 0)
+                .collect();
+            let cell = free[rng::index(r, free.len())];
+            grid.matrix[cell] = Self::draw(r).current();
+        }
+        grid
+    }
+
+    fn first_tile(&self, r: &mut dyn RngCore) -> Tile {
+        Self::draw(r)
+    }
+
+    fn next_tile(&self, r: &mut dyn RngCore, _tile: Tile, _max: u32) -> Tile {
+        Self::draw(r)
+    }
+
+    fn tile_outcomes(&self, _tile: Tile, _max: u32) -> Vec<(Tile, f64)> {
+        let p = TWO_IN_TEN as f64 / 10.0;
+        vec![(Tile::from_value(2), p), (Tile::from_value(4), 1.0 - p)]
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use nalgebra::Matrix4;
+
+    use crate::{model::direction::Direction, rng};
+
+    use super::*;
+
+    #[test]
+    fn shift_line_slides_all_the_way() {
+        let (res, mutated, combined) = TwentyFortyEight.shift_line(&[0, 0, 0, 2]);
+        assert_eq!(res, vec![2, 0, 0, 0]);
+        assert!(mutated);
+        assert!(!combined);
+    }
+
+    #[test]
+    fn shift_line_combines_every_tile_at_most_once() {
+        let (res, _, combined) = TwentyFortyEight.shift_line(&[2, 2, 2, 2]);
+        assert_eq!(res, vec![4, 4, 0, 0]);
+        assert!(combined);
+        let (res, _, _) = TwentyFortyEight.shift_line(&[4, 4, 8, 0]);
+        assert_eq!(res, vec![8, 8, 0, 0]);
+        let (res, _, _) = TwentyFortyEight.shift_line(&[2, 0, 2, 4]);
+        assert_eq!(res, vec![4, 4, 0, 0]);
+    }
+
+    #[test]
+    fn shift_line_does_not_move_packed_lines() {
+        let (_, mutated, _) = TwentyFortyEight.shift_line(&[2, 4, 0, 0]);
+        assert!(!mutated);
+    }
+
+    #[test]
+    fn tiles_spawn_anywhere() {
+        let m = Matrix4::new(2, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 4);
+        let g = Grid::from(m);
+        let outcomes = g.shift_outcomes(&TwentyFortyEight, Direction::Left, 2);
+        // the 4 moved left, leaving 14 free cells
+        assert_eq!(outcomes.len(), 14);
+        assert!(outcomes.iter().all(|(g, _)| g.matrix[(3, 0)] == 4));
+    }
+
+    #[test]
+    fn initial_grid_has_two_tiles() {
+        let mut r = rng::from_seed(42);
+        let g = TwentyFortyEight.initial_grid(&mut r, Size::default());
+        assert_eq!(g.empty_cells(), 14);
+        assert!(g.matrix.iter().all(|e| [0, 2, 4].contains(e)));
+    }
+
+    #[test]
+    fn score_counts_every_combination() {
+        // 2 + 2 makes 4, 4 + 4 makes 8
+        assert_eq!(TwentyFortyEight.tile_score(2), 0);
+        assert_eq!(TwentyFortyEight.tile_score(4), 4);
+        assert_eq!(TwentyFortyEight.tile_score(8), 16);
+    }
+
+    #[test]
+    fn tile_outcomes_sum_to_1() {
+        let total: f64 = TwentyFortyEight
+            .tile_outcomes(Tile::from_value(2), 2048)
+            .iter()
+            .map(|(_, p)| p)
+            .sum();
+        assert!((total - 1.0).abs() < 1e-9);
+    }
+}
@@ -0,0 +1,197 @@
+use std::{
+    fs,
+    io::ErrorKind,
+    path::{Path, PathBuf},
+};
+
+use anyhow::{bail, Context, Result};
+use serde::{Deserialize, Serialize};
+
+use crate::state::State;
+
+// to be bumped whenever the serialized State changes in an incompatible way, and with
+// replay::VERSION as a State replays its moves the way a Replay does
+pub const VERSION: u32 = 1;
+
+#[derive(Debug, PartialEq, Eq, Copy, Clone)]
+pub enum Slot {
+    // written when quitting, resumed on next launch
+    Auto,
+    Numbered(u8),
+}
+
+impl Slot {
+    fn file_name(self) -> String {
+        match self {
+            Slot::Auto => "autosave.json".to_string(),
+            Slot::Numbered(n) => format!("slot-{}.json", n),
+        }
+    }
+}
+
+#[derive(Serialize)]
+struct SaveFileRef<'a> {
+    version: u32,
+    state: &'a State,
+}
+
+// read first to report version mismatches rather than a cryptic deserialization error
+#[derive(Deserialize)]
+struct Header {
+    version: u32,
+}
+
+#[derive(Deserialize)]
+struct SaveFile {
+    state: State,
+}
+
+pub fn to_string(state: &State) -> Result<String> {
+    let file = SaveFileRef {
+        version: VERSION,
+        state,
+    };
+    serde_json::to_string(&file).context("failed to serialize the game")
+}
+
+pub fn from_str(s: &str) -> Result<State> {
+    let header: Header =
+        serde_json::from_str(s).context("save is corrupt, it has no format version")?;
+    if header.version != VERSION {
+        bail!(
+            "save was written with format version {} but this version of threes reads version {}",
+            header.version,
+            VERSION
+        );
+    }
+    let file: SaveFile = serde_json::from_str(s).context("save is corrupt")?;
+    Ok(file.state)
+}
+
+pub struct Saves {
+    dir: PathBuf,
+}
+
+impl Saves {
+    pub fn new<P: Into<PathBuf>>(dir: P) -> Saves {
+        Saves { dir: dir.into() }
+    }
+
+    // $XDG_DATA_HOME/threes/saves on linux
+    pub fn default_dir() -> Result<PathBuf> {
+        dirs::data_dir()
+            .map(|d| d.join("threes").join("saves"))
+            .context("no data directory found to store saves in")
+    }
+
+    pub fn path(&self, slot: Slot) -> PathBuf {
+        self.dir.join(slot.file_name())
+    }
+
+    pub fn save(&self, slot: Slot, state: &State) -> Result<PathBuf> {
+        let path = self.path(slot);
+        fs::create_dir_all(&self.dir)
+            .with_context(|| format!("failed to create {}", self.dir.display()))?;
+        write(&path, state)?;
+        Ok(path)
+    }
+
+    // none if nothing was saved in this slot
+    pub fn load(&self, slot: Slot) -> Result<Option<State>> {
+        let path = self.path(slot);
+        if !path.exists() {
+            return Ok(None);
+        }
+        read(&path).map(Some)
+    }
+
+    pub fn delete(&self, slot: Slot) -> Result<()> {
+        let path = self.path(slot);
+        match fs::remove_file(&path) {
+            Err(e) if e.kind() != ErrorKind::NotFound => {
+                Err(e).with_context(|| format!("failed to delete {}", path.display()))
+            }
+            _ => Ok(()),
+        }
+    }
+}
+
+pub fn write(path: &Path, state: &State) -> Result<()> {
+    let s = to_string(state)?;
+    fs::write(path, s).with_context(|| format!("failed to write {}", path.display()))
+}
+
+pub fn read(path: &Path) -> Result<State> {
+    let s =
+        fs::read_to_string(path).with_context(|| format!("failed to read {}", path.display()))?;
+    from_str(&s).with_context(|| format!("failed to load {}", path.display()))
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::model::direction::Direction;
+
+    use super::*;
+
+    #[test]
+    fn round_trip_preserves_the_game() {
+        let mut s = State::new(7);
+        s.shift(Direction::Left);
+        s.shift(Direction::Up);
+        let mut loaded = from_str(&to_string(&s).unwrap()).unwrap();
+        assert_eq!(loaded.grid(), s.grid());
+        assert_eq!(loaded.next_tile(), s.next_tile());
+        assert_eq!(loaded.score(), s.score());
+        assert_eq!(loaded.seed(), s.seed());
+        // rng and history are restored too
+        s.shift(Direction::Right);
+        loaded.shift(Direction::Right);
+        assert_eq!(loaded.grid(), s.grid());
+        assert!(loaded.undo());
+        assert!(loaded.undo());
+        assert!(loaded.undo());
+        assert!(!loaded.undo());
+    }
+
+    #[test]
+    fn from_str_rejects_other_versions() {
+        let s = to_string(&State::new(7)).unwrap().replacen(
+            &format!("\"version\":{}", VERSION),
+            "\"version\":999",
+            1,
+        );
+        let err = from_str(&s).err().unwrap();
+        assert!(err.to_string().contains("format version 999"));
+    }
+
+    #[test]
+    fn version_is_bumped_with_the_replay_one() {
+        assert_eq!(VERSION, crate::replay::VERSION);
+    }
+
+    #[test]
+    fn from_str_rejects_corrupt_saves() {
+        let s = to_string(&State::new(7)).unwrap();
+        assert!(from_str(&s[..s.len() / 2]).is_err());
+        assert!(from_str("{\"version\":1,\"state\":{}}").is_err());
+    }
+
+    #[test]
+    fn load_is_none_if_nothing_was_saved() {
+        let saves = Saves::new(std::env::temp_dir().join("threes-test-empty-saves"));
+        assert!(saves.load(Slot::Numbered(1)).unwrap().is_none());
+    }
+
+    #[test]
+    fn save_then_load_from_slot() {
+        let dir = std::env::temp_dir().join(format!("threes-test-saves-{}", std::process::id()));
+        let saves = Saves::new(&dir);
+        let s = State::new(7);
+        saves.save(Slot::Numbered(2), &s).unwrap();
+        let loaded = saves.load(Slot::Numbered(2)).unwrap().unwrap();
+        assert_eq!(loaded.grid(), s.grid());
+        saves.delete(Slot::Numbered(2)).unwrap();
+        assert!(saves.load(Slot::Numbered(2)).unwrap().is_none());
+        let _ = fs::remove_dir_all(dir);
+    }
+}
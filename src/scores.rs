@@ -0,0 +1,268 @@
+use std::{
+    fmt, fs,
+    path::{Path, PathBuf},
+    time::{SystemTime, UNIX_EPOCH},
+};
+
+use anyhow::{bail, Context, Result};
+use serde::{Deserialize, Serialize};
+
+use crate::{rules::RuleSet, state::State};
+
+// to be bumped whenever the serialized HighScores change in an incompatible way
+pub const VERSION: u32 = 1;
+// number of games kept in the table
+pub const CAPACITY: usize = 10;
+
+#[derive(Debug, PartialEq, Eq, Clone, Serialize, Deserialize)]
+pub struct Entry {
+    pub score: u64,
+    pub highest_tile: u32,
+    pub moves: usize,
+    // seconds since the unix epoch
+    pub timestamp: u64,
+    pub seed: u64,
+}
+
+impl Entry {
+    pub fn new(state: &State, timestamp: u64) -> Entry {
+        Entry {
+            score: state.score(),
+            highest_tile: state.grid().matrix.max(),
+            moves: state.moves(),
+            timestamp,
+            seed: state.seed(),
+        }
+    }
+
+    pub fn now(state: &State) -> Entry {
+        let timestamp = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .map(|d| d.as_secs())
+            .unwrap_or_default();
+        Self::new(state, timestamp)
+    }
+
+    // yyyy-mm-dd in utc, see http://howardhinnant.github.io/date_algorithms.html#civil_from_days
+    pub fn date(&self) -> String {
+        let days = (self.timestamp / 86400) as i64 + 719468;
+        let era = days.div_euclid(146097);
+        let doe = days.rem_euclid(146097);
+        let yoe = (doe - doe / 1460 + doe / 36524 - doe / 146096) / 365;
+        let doy = doe - (365 * yoe + yoe / 4 - yoe / 100);
+        let mp = (5 * doy + 2) / 153;
+        let day = doy - (153 * mp + 2) / 5 + 1;
+        let month = if mp < 10 { mp + 3 } else { mp - 9 };
+        let year = yoe + era * 400 + if month <= 2 { 1 } else { 0 };
+        format!("{:04}-{:02}-{:02}", year, month, day)
+    }
+}
+
+// best games first
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct HighScores {
+    entries: Vec<Entry>,
+}
+
+// as the scoreboard shows them
+impl fmt::Display for HighScores {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        writeln!(
+            f,
+            "{:>2} {:>8} {:>5} {:>6} {:<10} seed",
+            "#", "score", "tile", "moves", "date"
+        )?;
+        for (i, e) in self.entries.iter().enumerate() {
+            writeln!(
+                f,
+                "{:>2} {:>8} {:>5} {:>6} {:<10} {}",
+                i + 1,
+                e.score,
+                e.highest_tile,
+                e.moves,
+                e.date(),
+                e.seed
+            )?;
+        }
+        Ok(())
+    }
+}
+
+#[derive(Serialize)]
+struct ScoresFileRef<'a> {
+    version: u32,
+    scores: &'a HighScores,
+}
+
+// read first to report version mismatches rather than a cryptic deserialization error
+#[derive(Deserialize)]
+struct Header {
+    version: u32,
+}
+
+#[derive(Deserialize)]
+struct ScoresFile {
+    scores: HighScores,
+}
+
+impl HighScores {
+    pub fn entries(&self) -> &[Entry] {
+        &self.entries
+    }
+
+    // the 0-based rank of the entry, none if it didn't make the table
+    pub fn insert(&mut self, entry: Entry) -> Option<usize> {
+        // ties are ranked below the existing entries
+        let rank = self.entries.partition_point(|e| e.score >= entry.score);
+        if rank >= CAPACITY {
+            return None;
+        }
+        self.entries.insert(rank, entry);
+        self.entries.truncate(CAPACITY);
+        Some(rank)
+    }
+
+    // $XDG_DATA_HOME/threes/scores.json on linux, suffixed by the rules for other rule sets
+    pub fn default_path(rules: RuleSet) -> Result<PathBuf> {
+        let file = match rules {
+            RuleSet::Threes => "scores.json".to_string(),
+            other => format!("scores-{}.json", other),
+        };
+        dirs::data_dir()
+            .map(|d| d.join("threes").join(file))
+            .context("no data directory found to store scores in")
+    }
+
+    // empty if no scores were saved yet
+    pub fn read(path: &Path) -> Result<HighScores> {
+        if !path.exists() {
+            return Ok(HighScores::default());
+        }
+        let s = fs::read_to_string(path)
+            .with_context(|| format!("failed to read {}", path.display()))?;
+        Self::from_str(&s).with_context(|| format!("failed to load {}", path.display()))
+    }
+
+    pub fn write(&self, path: &Path) -> Result<()> {
+        if let Some(dir) = path.parent() {
+            fs::create_dir_all(dir)
+                .with_context(|| format!("failed to create {}", dir.display()))?;
+        }
+        let file = ScoresFileRef {
+            version: VERSION,
+            scores: self,
+        };
+        let s = serde_json::to_string(&file).context("failed to serialize the scores")?;
+        fs::write(path, s).with_context(|| format!("failed to write {}", path.display()))
+    }
+
+    fn from_str(s: &str) -> Result<HighScores> {
+        let header: Header =
+            serde_json::from_str(s).context("scores are corrupt, they have no format version")?;
+        if header.version != VERSION {
+            bail!(
+                "scores were written with format version {} but this version of threes reads version {}",
+                header.version,
+                VERSION
+            );
+        }
+        let file: ScoresFile = serde_json::from_str(s).context("scores are corrupt")?;
+        Ok(file.scores)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::model::direction::Direction;
+
+    use super::*;
+
+    fn entry(score: u64) -> Entry {
+        Entry {
+            score,
+            highest_tile: 48,
+            moves: 100,
+            timestamp: 0,
+            seed: score,
+        }
+    }
+
+    #[test]
+    fn insert_keeps_best_scores_first() {
+        let mut hs = HighScores::default();
+        assert_eq!(hs.insert(entry(10)), Some(0));
+        assert_eq!(hs.insert(entry(30)), Some(0));
+        assert_eq!(hs.insert(entry(20)), Some(1));
+        let scores: Vec<u64> = hs.entries().iter().map(|e| e.score).collect();
+        assert_eq!(scores, vec![30, 20, 10]);
+    }
+
+    #[test]
+    fn display_lists_every_entry() {
+        let mut hs = HighScores::default();
+        hs.insert(entry(10));
+        hs.insert(entry(1234));
+        let lines: Vec<String> = hs.to_string().lines().map(String::from).collect();
+        assert_eq!(lines.len(), 3);
+        assert_eq!(lines[1], " 1     1234    48    100 1970-01-01 1234");
+    }
+
+    #[test]
+    fn insert_ranks_ties_below() {
+        let mut hs = HighScores::default();
+        hs.insert(entry(10));
+        assert_eq!(hs.insert(entry(10)), Some(1));
+    }
+
+    #[test]
+    fn insert_drops_entries_past_capacity() {
+        let mut hs = HighScores::default();
+        for i in 1..=CAPACITY as u64 {
+            hs.insert(entry(i * 10));
+        }
+        assert_eq!(hs.insert(entry(5)), None);
+        assert_eq!(hs.insert(entry(15)), Some(CAPACITY - 1));
+        assert_eq!(hs.entries().len(), CAPACITY);
+        assert_eq!(hs.entries().last().unwrap().score, 15);
+    }
+
+    #[test]
+    fn entry_describes_the_game() {
+        let mut s =
+            State::from_notation("3,3,-,-/-,-,-,-/-,-,-,-/-,-,-,24 1", 7, RuleSet::Threes).unwrap();
+        s.shift(Direction::Left);
+        let e = Entry::new(&s, 0);
+        assert_eq!(e.score, s.score());
+        assert_eq!(e.highest_tile, 24);
+        assert_eq!(e.moves, 1);
+        assert_eq!(e.seed, 7);
+    }
+
+    #[test]
+    fn date_is_civil_utc() {
+        let mut e = entry(0);
+        assert_eq!(e.date(), "1970-01-01");
+        e.timestamp = 951782400;
+        assert_eq!(e.date(), "2000-02-29");
+        e.timestamp = 1792195200;
+        assert_eq!(e.date(), "2026-10-17");
+    }
+
+    #[test]
+    fn read_is_empty_if_nothing_was_written() {
+        let path = std::env::temp_dir().join("threes-test-no-scores.json");
+        assert!(HighScores::read(&path).unwrap().entries().is_empty());
+    }
+
+    #[test]
+    fn write_then_read() {
+        let path =
+            std::env::temp_dir().join(format!("threes-test-scores-{}.json", std::process::id()));
+        let mut hs = HighScores::default();
+        hs.insert(entry(10));
+        hs.write(&path).unwrap();
+        let read = HighScores::read(&path).unwrap();
+        assert_eq!(read.entries(), hs.entries());
+        let _ = fs::remove_file(path);
+    }
+}
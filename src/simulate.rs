@@ -0,0 +1,229 @@
+use std::{collections::BTreeMap, fmt};
+
+use anyhow::{bail, Result};
+use serde::Serialize;
+
+use crate::{ai::strategy::Strategy, model::size::Size, rules::RuleSet, state::State};
+
+#[derive(Debug, Clone, Serialize)]
+pub struct GameResult {
+    pub seed: u64,
+    pub score: u64,
+    pub highest_tile: u32,
+    pub moves: usize,
+}
+
+// plays a game to its end, the strategy giving up ends it too
+pub fn play<S: Strategy + ?Sized>(
+    strategy: &mut S,
+    seed: u64,
+    size: Size,
+    rules: RuleSet,
+) -> Result<GameResult> {
+    let mut state = State::with_rules(seed, size, rules);
+    while let Some(dir) = strategy.choose(&state)? {
+        if !state.legal_moves().contains(&dir) {
+            bail!("{} is not a legal move in {}", dir, state.notation());
+        }
+        state.shift(dir);
+    }
+    Ok(GameResult {
+        seed,
+        score: state.score(),
+        highest_tile: state.grid().matrix.max(),
+        moves: state.moves(),
+    })
+}
+
+// plays games with consecutive seeds so that runs are reproducible
+pub fn simulate<S: Strategy + ?Sized>(
+    strategy: &mut S,
+    games: usize,
+    first_seed: u64,
+    size: Size,
+    rules: RuleSet,
+) -> Result<Summary> {
+    let results = (0..games as u64)
+        .map(|i| play(strategy, first_seed.wrapping_add(i), size, rules))
+        .collect::<Result<Vec<_>>>()?;
+    Ok(Summary::new(&results))
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct Stats {
+    pub mean: f64,
+    pub min: u64,
+    pub p10: u64,
+    pub p25: u64,
+    pub median: u64,
+    pub p75: u64,
+    pub p90: u64,
+    pub max: u64,
+}
+
+impl Stats {
+    pub fn new(mut values: Vec<u64>) -> Stats {
+        values.sort_unstable();
+        let mean = if values.is_empty() {
+            0.0
+        } else {
+            values.iter().sum::<u64>() as f64 / values.len() as f64
+        };
+        Stats {
+            mean,
+            min: percentile(&values, 0),
+            p10: percentile(&values, 10),
+            p25: percentile(&values, 25),
+            median: percentile(&values, 50),
+            p75: percentile(&values, 75),
+            p90: percentile(&values, 90),
+            max: percentile(&values, 100),
+        }
+    }
+}
+
+// nearest rank, of sorted values
+fn percentile(sorted: &[u64], p: usize) -> u64 {
+    if sorted.is_empty() {
+        return 0;
+    }
+    let rank = (p * sorted.len()).div_ceil(100).max(1);
+    sorted[rank - 1]
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct Summary {
+    pub games: usize,
+    pub score: Stats,
+    pub moves: Stats,
+    // number of games which ended with each highest tile
+    pub highest_tiles: BTreeMap<u32, usize>,
+}
+
+impl Summary {
+    pub fn new(results: &[GameResult]) -> Summary {
+        let mut highest_tiles = BTreeMap::new();
+        for r in results {
+            *highest_tiles.entry(r.highest_tile).or_insert(0) += 1;
+        }
+        Summary {
+            games: results.len(),
+            score: Stats::new(results.iter().map(|r| r.score).collect()),
+            moves: Stats::new(results.iter().map(|r| r.moves as u64).collect()),
+            highest_tiles,
+        }
+    }
+}
+
+impl fmt::Display for Summary {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        writeln!(f, "games: {}", self.games)?;
+        writeln!(
+            f,
+            "{:<6} {:>10} {:>8} {:>8} {:>8} {:>8} {:>8} {:>8} {:>8}",
+            "", "mean", "min", "p10", "p25", "median", "p75", "p90", "max"
+        )?;
+        for (name, s) in [("score", &self.score), ("moves", &self.moves)] {
+            writeln!(
+                f,
+                "{:<6} {:>10.1} {:>8} {:>8} {:>8} {:>8} {:>8} {:>8} {:>8}",
+                name, s.mean, s.min, s.p10, s.p25, s.median, s.p75, s.p90, s.max
+            )?;
+        }
+        writeln!(f, "highest tile:")?;
+        for (tile, count) in self.highest_tiles.iter().rev() {
+            let share = 100.0 * *count as f64 / self.games as f64;
+            writeln!(f, "{:>6} {:>8} {:>7.1}%", tile, count, share)?;
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{ai::strategy::Random, model::direction::Direction};
+
+    use super::*;
+
+    #[test]
+    fn percentile_is_nearest_rank() {
+        let values: Vec<u64> = (1..=10).collect();
+        assert_eq!(percentile(&values, 0), 1);
+        assert_eq!(percentile(&values, 25), 3);
+        assert_eq!(percentile(&values, 50), 5);
+        assert_eq!(percentile(&values, 90), 9);
+        assert_eq!(percentile(&values, 100), 10);
+        assert_eq!(percentile(&[], 50), 0);
+    }
+
+    #[test]
+    fn stats_of_values() {
+        let s = Stats::new(vec![4, 1, 3, 2]);
+        assert_eq!(s.mean, 2.5);
+        assert_eq!(s.min, 1);
+        assert_eq!(s.median, 2);
+        assert_eq!(s.max, 4);
+    }
+
+    #[test]
+    fn simulate_plays_every_game() {
+        let summary = simulate(
+            &mut Random::new(7),
+            5,
+            7,
+            Size::default(),
+            RuleSet::default(),
+        )
+        .unwrap();
+        assert_eq!(summary.games, 5);
+        assert_eq!(summary.highest_tiles.values().sum::<usize>(), 5);
+        assert!(summary.moves.min > 0);
+    }
+
+    #[test]
+    fn simulate_is_reproducible() {
+        let s1 = simulate(
+            &mut Random::new(7),
+            3,
+            7,
+            Size::default(),
+            RuleSet::default(),
+        )
+        .unwrap();
+        let s2 = simulate(
+            &mut Random::new(7),
+            3,
+            7,
+            Size::default(),
+            RuleSet::default(),
+        )
+        .unwrap();
+        assert_eq!(s1.score, s2.score);
+        assert_eq!(s1.highest_tiles, s2.highest_tiles);
+    }
+
+    #[test]
+    fn simulate_plays_2048() {
+        let summary = simulate(
+            &mut Random::new(7),
+            3,
+            7,
+            Size::default(),
+            RuleSet::TwentyFortyEight,
+        )
+        .unwrap();
+        assert!(summary.highest_tiles.keys().all(|t| t.is_power_of_two()));
+    }
+
+    #[test]
+    fn play_rejects_illegal_moves() {
+        // down can't stay legal forever
+        struct Stubborn;
+        impl Strategy for Stubborn {
+            fn choose(&mut self, _: &State) -> Result<Option<Direction>> {
+                Ok(Some(Direction::Down))
+            }
+        }
+        assert!(play(&mut Stubborn, 7, Size::default(), RuleSet::default()).is_err());
+    }
+}
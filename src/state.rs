@@ -1,118 +1,434 @@
-use rand::Rng;
+use serde::{Deserialize, Serialize};
 
 use crate::{
-    math::series::Series, model::direction::Direction, model::grid::Grid, model::tile::Tile,
+    history::{History, UndoPolicy},
+    model::direction::Direction,
+    model::grid::Grid,
+    model::outcome::MoveOutcome,
+    model::size::Size,
+    model::tile::Tile,
+    replay::{Replay, Step},
+    rng::{self, GameRng},
+    rules::RuleSet,
 };
 
+#[derive(Clone, Serialize, Deserialize)]
 pub struct State {
-    pub grid: Grid,
-    pub tile: Tile,
-    pub game_over: bool,
-    past_grid: Grid,
-    past_tile: Tile,
-    series: Series,
+    grid: Grid,
+    tile: Tile,
+    game_over: bool,
+    history: History<Snapshot>,
+    rules: RuleSet,
+    seed: u64,
+    rng: GameRng,
+    // where the game started from, for replays
+    start: Snapshot,
+    // can hold undone steps past moves, which are dropped by the next move
+    steps: Vec<Step>,
+    moves: usize,
+}
+
+// everything a move changes, restored as a whole by undo and redo
+#[derive(Clone, Serialize, Deserialize)]
+struct Snapshot {
+    grid: Grid,
+    tile: Tile,
+    game_over: bool,
+    rng: GameRng,
+    moves: usize,
 }
 
 impl State {
-    pub fn from_base_values<R: Rng + ?Sized, I>(r: &mut R, base_values: I) -> State
+    pub fn new(seed: u64) -> State {
+        Self::with_size(seed, Size::default())
+    }
+
+    pub fn with_size(seed: u64, size: Size) -> State {
+        Self::with_rules(seed, size, RuleSet::default())
+    }
+
+    pub fn with_rules(seed: u64, size: Size, rules: RuleSet) -> State {
+        let mut r = rng::from_seed(seed);
+        let g = rules.rules().initial_grid(&mut r, size);
+        let t = rules.rules().first_tile(&mut r);
+        Self::from_parts(seed, rules, r, g, t)
+    }
+
+    // a threes game starting from a grid built from these numbers of empty cells, 1s, 2s and 3s
+    pub fn from_base_values<I>(seed: u64, base_values: I, size: Size) -> State
     where
         I: IntoIterator<Item = u32>,
     {
-        let g = Grid::rand(r, base_values);
-        let t = Tile::new(r);
+        let mut r = rng::from_seed(seed);
+        let g = Grid::rand(&mut r, base_values, size);
+        let t = RuleSet::Threes.rules().first_tile(&mut r);
+        Self::from_parts(seed, RuleSet::Threes, r, g, t)
+    }
+
+    // starts from a given position rather than a random one
+    pub fn from_position(seed: u64, grid: Grid, next_tile: u32, rules: RuleSet) -> State {
+        Self::from_parts(
+            seed,
+            rules,
+            rng::from_seed(seed),
+            grid,
+            Tile::from_value(next_tile),
+        )
+    }
+
+    pub(crate) fn from_parts(
+        seed: u64,
+        rules: RuleSet,
+        rng: GameRng,
+        grid: Grid,
+        tile: Tile,
+    ) -> State {
+        let game_over = grid.game_over(rules.rules());
         State {
-            grid: g,
-            tile: t,
-            game_over: false,
-            past_grid: g,
-            past_tile: t,
-            series: Series::new(1, 2, 2),
+            grid: grid.clone(),
+            tile,
+            game_over,
+            history: History::new(UndoPolicy::Unlimited),
+            rules,
+            seed,
+            rng: rng.clone(),
+            start: Snapshot {
+                grid,
+                tile,
+                game_over,
+                rng,
+                moves: 0,
+            },
+            steps: Vec::new(),
+            moves: 0,
         }
     }
 
-    pub fn shift<R: Rng + ?Sized>(&mut self, r: &mut R, direction: Direction) -> &mut State {
-        self.past_grid = self.grid;
-        self.past_tile = self.tile;
+    pub fn undo_policy(mut self, policy: UndoPolicy) -> State {
+        self.history = History::new(policy);
+        self
+    }
+
+    pub fn shift(&mut self, direction: Direction) -> MoveOutcome {
+        let past = self.snapshot();
         let new_tile = self.tile.current();
-        let (new_grid, next_tile_inserted, game_over) = self.grid.shift(r, direction, new_tile);
-        self.grid = new_grid;
-        self.game_over = game_over;
-        if next_tile_inserted {
+        let rules = self.rules.rules();
+        let outcome = self.grid.shift(rules, &mut self.rng, direction, new_tile);
+        self.grid = outcome.grid.clone();
+        self.game_over = outcome.game_over;
+        if outcome.spawned.is_some() {
             let max = self.grid.matrix.max();
-            self.tile = self.tile.next(r, max);
+            self.tile = rules.next_tile(&mut self.rng, self.tile, max);
         }
-        self
+        if self.grid != past.grid || self.game_over != past.game_over {
+            self.steps.truncate(self.moves);
+            self.steps.push(Step {
+                direction,
+                spawned: outcome.spawned.map(|spawn| spawn.value),
+            });
+            self.moves += 1;
+            self.history.record(past);
+        }
+        outcome
     }
 
-    pub fn shift_back(&mut self) -> &mut State {
-        self.grid = self.past_grid;
-        self.tile = self.past_tile;
-        self
+    // returns whether there was a move to undo
+    pub fn undo(&mut self) -> bool {
+        match self.history.undo(self.snapshot()) {
+            Ok(past) => {
+                self.restore(past);
+                true
+            }
+            Err(_) => false,
+        }
     }
 
-    pub fn score(&self) -> u64 {
-        self.grid.matrix.fold(0, |acc, e| {
-            if e < 3 {
-                acc
-            } else {
-                let rank = self.series.n(e);
-                acc + (3_u64).pow(rank - 1)
+    // returns whether there was an undone move to redo
+    pub fn redo(&mut self) -> bool {
+        match self.history.redo(self.snapshot()) {
+            Ok(future) => {
+                self.restore(future);
+                true
             }
-        })
+            Err(_) => false,
+        }
+    }
+
+    pub fn can_undo(&self) -> bool {
+        self.history.can_undo()
+    }
+
+    pub fn can_redo(&self) -> bool {
+        self.history.can_redo()
+    }
+
+    fn snapshot(&self) -> Snapshot {
+        Snapshot {
+            grid: self.grid.clone(),
+            tile: self.tile,
+            game_over: self.game_over,
+            rng: self.rng.clone(),
+            moves: self.moves,
+        }
+    }
+
+    fn restore(&mut self, snapshot: Snapshot) {
+        self.grid = snapshot.grid;
+        self.tile = snapshot.tile;
+        self.game_over = snapshot.game_over;
+        self.rng = snapshot.rng;
+        self.moves = snapshot.moves;
+    }
+
+    pub fn replay(&self) -> Replay {
+        Replay::new(
+            self.seed,
+            self.rules,
+            self.start.grid.clone(),
+            self.start.tile,
+            self.start.rng.clone(),
+            self.steps[..self.moves].to_vec(),
+        )
+    }
+
+    pub(crate) fn last_step(&self) -> Option<&Step> {
+        self.moves.checked_sub(1).map(|i| &self.steps[i])
+    }
+
+    pub fn moves(&self) -> usize {
+        self.moves
+    }
+
+    pub fn seed(&self) -> u64 {
+        self.seed
+    }
+
+    pub fn rules(&self) -> RuleSet {
+        self.rules
+    }
+
+    pub fn grid(&self) -> &Grid {
+        &self.grid
+    }
+
+    pub fn size(&self) -> Size {
+        self.grid.size()
+    }
+
+    pub fn next_tile(&self) -> u32 {
+        self.tile.current()
+    }
+
+    pub fn is_game_over(&self) -> bool {
+        self.game_over
+    }
+
+    pub fn legal_moves(&self) -> Vec<Direction> {
+        self.grid.legal_moves(self.rules.rules())
+    }
+
+    pub fn score(&self) -> u64 {
+        self.grid.score(self.rules.rules())
+    }
+
+    pub fn tile(&self) -> &Tile {
+        &self.tile
     }
 }
 
 #[cfg(test)]
 mod tests {
-    use nalgebra::{Matrix4, SMatrix};
-    use rand::rngs::OsRng;
+    use nalgebra::Matrix4;
+
+    use crate::math::series::Series;
 
     use super::*;
 
-    fn new_grid(matrix: SMatrix<u32, 4, 4>) -> Grid {
-        Grid { matrix }
+    fn new_state(m: Matrix4<u32>) -> State {
+        let mut r = rng::from_seed(42);
+        let t = Tile::new(&mut r);
+        State::from_parts(42, RuleSet::Threes, r, Grid::from(m), t)
     }
 
-    fn new_state<R: Rng + ?Sized>(r: &mut R, m: SMatrix<u32, 4, 4>) -> State {
-        let g = new_grid(m);
-        let t = Tile::new(r);
-        State {
-            grid: g,
-            tile: t,
-            game_over: false,
-            past_grid: g,
-            past_tile: t,
-            series: Series::new(1, 2, 2),
+    fn play(seed: u64, moves: &[Direction]) -> State {
+        let mut s = State::new(seed);
+        for m in moves {
+            s.shift(*m);
+        }
+        s
+    }
+
+    #[test]
+    fn same_seed_and_moves_yield_same_game() {
+        let moves = [
+            Direction::Left,
+            Direction::Up,
+            Direction::Right,
+            Direction::Down,
+        ]
+        .repeat(10);
+        let s1 = play(7, &moves);
+        let s2 = play(7, &moves);
+        assert_eq!(s1.grid(), s2.grid());
+        assert_eq!(s1.next_tile(), s2.next_tile());
+        assert_eq!(s1.score(), s2.score());
+    }
+
+    #[test]
+    fn different_seeds_yield_different_games() {
+        let g1 = State::new(1).grid().clone();
+        let g2 = State::new(2).grid().clone();
+        assert_ne!(g1, g2);
+    }
+
+    #[test]
+    fn undo_restores_every_past_move() {
+        let moves = [Direction::Left, Direction::Up, Direction::Right];
+        let mut s = State::new(7);
+        let mut grids = vec![s.grid().clone()];
+        for m in moves {
+            s.shift(m);
+            grids.push(s.grid().clone());
+        }
+        grids.pop();
+        while let Some(g) = grids.pop() {
+            assert!(s.undo());
+            assert_eq!(s.grid(), &g);
+        }
+        assert!(!s.undo());
+    }
+
+    #[test]
+    fn redo_replays_the_same_game() {
+        let moves = [Direction::Left, Direction::Up, Direction::Right];
+        let played = play(7, &moves);
+        let mut s = play(7, &moves);
+        for _ in moves {
+            s.undo();
         }
+        for _ in moves {
+            assert!(s.redo());
+        }
+        assert!(!s.redo());
+        assert_eq!(s.grid(), played.grid());
+        assert_eq!(s.next_tile(), played.next_tile());
+    }
+
+    #[test]
+    fn undo_then_same_move_spawns_same_tile() {
+        let mut s = State::new(7);
+        s.shift(Direction::Left);
+        let shifted = s.grid().clone();
+        s.undo();
+        s.shift(Direction::Left);
+        assert_eq!(s.grid(), &shifted);
+    }
+
+    #[test]
+    fn game_over_as_soon_as_no_move_is_left() {
+        // only the first row and the last column can move, and once the first row moved right
+        // the 1 spawning at its end combines with nothing
+        let m = Matrix4::new(12, 24, 12, 0, 6, 3, 6, 3, 3, 6, 3, 6, 6, 3, 6, 3);
+        let mut s = State::from_position(42, Grid::from(m), 1, RuleSet::Threes);
+        assert_eq!(s.legal_moves(), vec![Direction::Up, Direction::Right]);
+        assert!(!s.is_game_over());
+        s.shift(Direction::Right);
+        assert!(s.legal_moves().is_empty());
+        assert!(s.is_game_over());
+    }
+
+    #[test]
+    fn a_stuck_position_is_over_from_the_start() {
+        assert!(new_state(Matrix4::repeat(1)).is_game_over());
+    }
+
+    #[test]
+    fn undo_restores_game_over() {
+        let m = Matrix4::new(12, 24, 12, 0, 6, 3, 6, 3, 3, 6, 3, 6, 6, 3, 6, 3);
+        let mut s = State::from_position(42, Grid::from(m), 1, RuleSet::Threes);
+        s.shift(Direction::Right);
+        assert!(s.is_game_over());
+        assert!(s.undo());
+        assert!(!s.is_game_over());
+    }
+
+    #[test]
+    fn no_op_moves_are_not_recorded() {
+        let m = Matrix4::new(0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 3, 6, 12, 24);
+        let mut s = new_state(m);
+        s.shift(Direction::Down);
+        assert!(!s.can_undo());
+    }
+
+    #[test]
+    fn disabled_undo_policy_prevents_undo() {
+        let mut s = State::new(7).undo_policy(UndoPolicy::Disabled);
+        s.shift(Direction::Left);
+        assert!(!s.undo());
+    }
+
+    #[test]
+    fn legal_moves_lists_mutating_directions() {
+        let m = Matrix4::new(0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 3, 6, 12, 24);
+        let s = new_state(m);
+        assert_eq!(s.legal_moves(), vec![Direction::Up]);
+    }
+
+    #[test]
+    fn legal_moves_is_empty_if_immutable() {
+        let s = new_state(Matrix4::repeat(1));
+        assert!(s.legal_moves().is_empty());
     }
 
     #[test]
     fn score_is_0_if_no_gt_3() -> () {
-        let mut r = OsRng;
         for i in 0..3 {
-            let s = new_state(&mut r, Matrix4::repeat(i));
+            let s = new_state(Matrix4::repeat(i));
             assert_eq!(s.score(), 0);
         }
     }
 
     #[test]
     fn score_is_correct_if_gt_3() -> () {
-        let mut r = OsRng;
         let series = Series::new(1, 2, 2);
         let grid_size = 16;
         for i in 2..10 {
             let u_i = series.u_n(i);
             let m = Matrix4::repeat(u_i);
-            let s = new_state(&mut r, m);
-            assert_eq!(s.score(), 3_u64.pow(i - 1) as u64 * grid_size);
+            let s = new_state(m);
+            assert_eq!(s.score(), 3_u64.pow(i - 1) * grid_size);
         }
     }
 
     #[test]
     fn score_is_in_concordance_with_screenshot() -> () {
-        let mut r = OsRng;
         let m = Matrix4::new(2, 3, 1, 3, 3, 1, 3, 2, 6, 3, 24, 2, 3, 48, 192, 96);
-        let s = new_state(&mut r, m);
+        let s = new_state(m);
         assert_eq!(s.score(), 3267);
     }
+
+    #[test]
+    fn with_size_plays_on_the_requested_board() {
+        let size = Size { cols: 5, rows: 3 };
+        let mut s = State::with_size(42, size);
+        assert_eq!(s.size(), size);
+        while let Some(d) = s.legal_moves().first().copied() {
+            s.shift(d);
+            assert_eq!(s.size(), size);
+        }
+        assert!(s.moves() > 0);
+        assert!(s.is_game_over());
+    }
+
+    #[test]
+    fn with_rules_plays_2048() {
+        let mut s = State::with_rules(42, Size::default(), RuleSet::TwentyFortyEight);
+        assert_eq!(s.rules(), RuleSet::TwentyFortyEight);
+        assert_eq!(s.grid().empty_cells(), 14);
+        s.shift(s.legal_moves()[0]);
+        // a 2 or a 4 spawned, possibly after combining two tiles
+        assert!((12..=13).contains(&s.grid().empty_cells()));
+        let replayed = s.replay().states().unwrap();
+        assert_eq!(replayed.last().unwrap().grid(), s.grid());
+    }
 }
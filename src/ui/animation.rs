@@ -0,0 +1,133 @@
+use std::time::{Duration, Instant};
+
+use ratatui::{
+    buffer::{Buffer, Cell},
+    layout::Rect,
+    widgets::Widget,
+};
+use threes::model::{direction::Direction, grid::Grid, outcome::MoveOutcome};
+
+use super::{
+    board::{cell_areas, Board},
+    square::Square,
+    theme::Theme,
+};
+
+// a move being played out on the board, the first half sliding, the second merging and spawning
+pub struct Animation {
+    outcome: MoveOutcome,
+    started: Instant,
+    duration: Duration,
+    // the theme the move started in
+    theme: Theme,
+}
+
+impl Animation {
+    pub fn new(outcome: MoveOutcome, duration: Duration, theme: &Theme) -> Animation {
+        Animation {
+            outcome,
+            started: Instant::now(),
+            duration,
+            theme: theme.clone(),
+        }
+    }
+
+    pub fn is_done(&self) -> bool {
+        self.started.elapsed() >= self.duration
+    }
+
+    // from 0 to 1
+    fn progress(&self) -> f64 {
+        if self.duration.is_zero() {
+            return 1.0;
+        }
+        (self.started.elapsed().as_secs_f64() / self.duration.as_secs_f64()).min(1.0)
+    }
+
+    // where tiles enter the board from, one cell past its trailing edge
+    fn entry_offset(&self, cell: Rect) -> (f64, f64) {
+        let (w, h) = (cell.width as f64, cell.height as f64);
+        match self.outcome.direction {
+            Direction::Up => (0.0, h),
+            Direction::Down => (0.0, -h),
+            Direction::Left => (w, 0.0),
+            Direction::Right => (-w, 0.0),
+        }
+    }
+}
+
+impl Widget for &Animation {
+    fn render(self, area: Rect, buf: &mut Buffer) {
+        let grid = &self.outcome.grid;
+        let size = grid.size();
+        let cells = cell_areas(size, area);
+        let board = cells[0][0]
+            .union(cells[size.rows - 1][size.cols - 1])
+            .intersection(buf.area);
+        Board::new(&Grid::empty(size), &self.theme).render(area, buf);
+
+        let progress = self.progress();
+        if progress < 0.5 {
+            let t = progress * 2.0;
+            for motion in &self.outcome.motions {
+                let from = cells[motion.from.0][motion.from.1];
+                let to = cells[motion.to.0][motion.to.1];
+                let offset = (
+                    (from.x as f64 - to.x as f64) * (1.0 - t),
+                    (from.y as f64 - to.y as f64) * (1.0 - t),
+                );
+                let square = Square::from_elem(motion.value, &self.theme);
+                render_moved(square, to, offset, board, buf);
+            }
+            return;
+        }
+
+        let t = (progress - 0.5) * 2.0;
+        for (i, row) in cells.iter().enumerate() {
+            for (j, cell) in row.iter().enumerate() {
+                let value = grid.matrix[(i, j)];
+                if value == 0 || self.outcome.spawned.is_some_and(|s| s.at == (i, j)) {
+                    continue;
+                }
+                let square = Square::from_elem(value, &self.theme);
+                // merged tiles pulse once
+                let square = if t < 0.5 && self.outcome.merges.iter().any(|m| m.at == (i, j)) {
+                    square.pulse()
+                } else {
+                    square
+                };
+                square.render(*cell, buf);
+            }
+        }
+        if let Some(spawn) = self.outcome.spawned {
+            let cell = cells[spawn.at.0][spawn.at.1];
+            let (x, y) = self.entry_offset(cell);
+            let square = Square::from_elem(spawn.value, &self.theme);
+            render_moved(square, cell, (x * (1.0 - t), y * (1.0 - t)), board, buf);
+        }
+    }
+}
+
+// renders a square offset from its area, only where it lands within the board
+fn render_moved(square: Square, area: Rect, offset: (f64, f64), board: Rect, buf: &mut Buffer) {
+    let mut scratch = Buffer::empty(area);
+    square.render(area, &mut scratch);
+    let (dx, dy) = (offset.0.round() as i32, offset.1.round() as i32);
+    for y in area.top()..area.bottom() {
+        for x in area.left()..area.right() {
+            let cell = scratch.get(x, y);
+            // the square's margin is left blank so as not to cover its neighbours
+            if *cell == Cell::default() {
+                continue;
+            }
+            let (x, y) = (x as i32 + dx, y as i32 + dy);
+            if x >= board.left() as i32
+                && x < board.right() as i32
+                && y >= board.top() as i32
+                && y < board.bottom() as i32
+            {
+                *buf.get_mut(x as u16, y as u16) = cell.clone();
+            }
+        }
+    }
+}
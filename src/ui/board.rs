@@ -0,0 +1,57 @@
+use ratatui::{
+    buffer::Buffer,
+    layout::{Direction, Rect},
+    style::Style,
+    widgets::Widget,
+};
+use threes::model::{grid::Grid, size::Size};
+
+use super::{layouts::tiles_layout, square::Square, theme::Theme};
+
+// the grid's tiles, laid out as squares
+pub struct Board<'a> {
+    grid: &'a Grid,
+    theme: &'a Theme,
+}
+
+impl<'a> Board<'a> {
+    pub fn new(grid: &'a Grid, theme: &'a Theme) -> Board<'a> {
+        Board { grid, theme }
+    }
+}
+
+impl<'a> Widget for Board<'a> {
+    fn render(self, area: Rect, buf: &mut Buffer) {
+        let size = self.grid.size();
+        let cells = cell_areas(size, area);
+        if let Some(background) = self.theme.board {
+            let board = cells[0][0].union(cells[size.rows - 1][size.cols - 1]);
+            buf.set_style(board.intersection(buf.area), Style::new().bg(background));
+        }
+        for (i, row) in cells.iter().enumerate() {
+            for (j, square_area) in row.iter().enumerate() {
+                let elem = self.grid.matrix[(i, j)];
+                Square::from_elem(elem, self.theme).render(*square_area, buf);
+            }
+        }
+    }
+}
+
+// the area of every cell of a board of this size, row by row
+pub fn cell_areas(size: Size, area: Rect) -> Vec<Vec<Rect>> {
+    let row_layout = tiles_layout(size.rows, Direction::Vertical).split(area);
+    let col_layout = tiles_layout(size.cols, Direction::Horizontal);
+    // ignore min 0
+    row_layout
+        .iter()
+        .take(size.rows)
+        .map(|row| {
+            col_layout
+                .split(*row)
+                .iter()
+                .take(size.cols)
+                .copied()
+                .collect()
+        })
+        .collect()
+}
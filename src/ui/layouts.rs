@@ -1,9 +1,8 @@
 use once_cell::sync::Lazy;
-use ratatui::layout::{Constraint, Direction, Layout};
+use ratatui::layout::{Constraint, Direction, Layout, Rect};
 
 pub const TILE_HEIGHT: u16 = 7;
 pub const TILE_WIDTH: u16 = 14;
-pub const TILE_NUMBER: u16 = 4;
 
 // having to use lazy static because Layout::new is not const and Layout's fields are private
 pub static MAIN_LAYOUT: Lazy<Layout> = Lazy::new(|| {
@@ -12,7 +11,6 @@ pub static MAIN_LAYOUT: Lazy<Layout> = Lazy::new(|| {
         [
             Constraint::Length(2),
             Constraint::Length(TILE_HEIGHT),
-            Constraint::Length(40),
             Constraint::Min(0),
         ],
     )
@@ -31,38 +29,31 @@ pub static HORIZONTAL_SEP: Lazy<Layout> = Lazy::new(|| {
     )
 });
 
-pub static GAME_LAYOUT_H: Lazy<Layout> = Lazy::new(|| {
+// the board's area followed by what's left, the board being tiles wide or high
+pub fn game_layout(tiles: usize, dir: Direction) -> Layout {
+    let tile = if dir == Direction::Horizontal {
+        TILE_WIDTH
+    } else {
+        TILE_HEIGHT
+    };
     Layout::new(
-        Direction::Horizontal,
-        [
-            Constraint::Max(TILE_WIDTH * TILE_NUMBER),
-            Constraint::Min(0),
-        ],
-    )
-});
-
-pub static GAME_LAYOUT_V: Lazy<Layout> = Lazy::new(|| {
-    Layout::new(
-        Direction::Vertical,
-        [
-            Constraint::Max(TILE_HEIGHT * TILE_NUMBER),
-            Constraint::Min(0),
-        ],
+        dir,
+        [Constraint::Max(tile * tiles as u16), Constraint::Min(0)],
     )
-});
+}
 
-pub static ROW_LAYOUT: Lazy<Layout> = Lazy::new(|| {
-    Layout::new(
-        Direction::Vertical,
-        [
-            Constraint::Length(TILE_HEIGHT),
-            Constraint::Length(TILE_HEIGHT),
-            Constraint::Length(TILE_HEIGHT),
-            Constraint::Length(TILE_HEIGHT),
-            Constraint::Min(0),
-        ],
-    )
-});
+// one constraint per tile, the last one taking up what's left
+pub fn tiles_layout(tiles: usize, dir: Direction) -> Layout {
+    let tile = if dir == Direction::Horizontal {
+        TILE_WIDTH
+    } else {
+        TILE_HEIGHT
+    };
+    let constraints = (0..tiles)
+        .map(|_| Constraint::Length(tile))
+        .chain([Constraint::Min(0)]);
+    Layout::new(dir, constraints)
+}
 
 pub fn popup_layout(percent: u16, dir: Direction) -> Layout {
     Layout::new(
@@ -74,3 +65,8 @@ pub fn popup_layout(percent: u16, dir: Direction) -> Layout {
         ],
     )
 }
+
+pub fn centered_rect(percent_x: u16, percent_y: u16, r: Rect) -> Rect {
+    let popup_layout_v = popup_layout(percent_y, Direction::Vertical).split(r);
+    popup_layout(percent_x, Direction::Horizontal).split(popup_layout_v[1])[1]
+}
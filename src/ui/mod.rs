@@ -1,3 +1,8 @@
+pub mod animation;
+pub mod board;
 pub mod layouts;
+pub mod palette;
+pub mod scoreboard;
 pub mod square;
+pub mod terminal;
 pub mod theme;
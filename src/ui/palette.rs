@@ -0,0 +1,225 @@
+use std::env;
+
+use clap::ValueEnum;
+use ratatui::{buffer::Buffer, style::Color};
+
+// how many colours the terminal can show
+#[derive(Debug, PartialEq, Eq, Copy, Clone, ValueEnum)]
+pub enum ColorDepth {
+    #[value(name = "truecolor")]
+    TrueColor,
+    #[value(name = "256")]
+    Ansi256,
+    #[value(name = "16")]
+    Ansi16,
+    // no colour at all, tiles are told apart by bold, reverse and underline
+    #[value(name = "mono")]
+    Monochrome,
+}
+
+// the 16 colours as xterm draws them by default
+const ANSI16: [(Color, (u8, u8, u8)); 16] = [
+    (Color::Black, (0, 0, 0)),
+    (Color::Red, (205, 0, 0)),
+    (Color::Green, (0, 205, 0)),
+    (Color::Yellow, (205, 205, 0)),
+    (Color::Blue, (0, 0, 238)),
+    (Color::Magenta, (205, 0, 205)),
+    (Color::Cyan, (0, 205, 205)),
+    (Color::Gray, (229, 229, 229)),
+    (Color::DarkGray, (127, 127, 127)),
+    (Color::LightRed, (255, 0, 0)),
+    (Color::LightGreen, (0, 255, 0)),
+    (Color::LightYellow, (255, 255, 0)),
+    (Color::LightBlue, (92, 92, 255)),
+    (Color::LightMagenta, (255, 0, 255)),
+    (Color::LightCyan, (0, 255, 255)),
+    (Color::White, (255, 255, 255)),
+];
+
+// the levels of every channel in the 6x6x6 cube of the 256-colour palette
+const CUBE_LEVELS: [u8; 6] = [0, 95, 135, 175, 215, 255];
+
+impl ColorDepth {
+    pub fn detect() -> ColorDepth {
+        Self::from_env(|name| env::var(name).ok())
+    }
+
+    fn from_env(var: impl Fn(&str) -> Option<String>) -> ColorDepth {
+        // https://no-color.org
+        if var("NO_COLOR").is_some_and(|v| !v.is_empty()) {
+            return ColorDepth::Monochrome;
+        }
+        let colorterm = var("COLORTERM").unwrap_or_default();
+        let term = var("TERM").unwrap_or_default();
+        if colorterm == "truecolor" || colorterm == "24bit" || term.ends_with("-direct") {
+            ColorDepth::TrueColor
+        } else if var("WT_SESSION").is_some() {
+            // windows terminal doesn't set the others
+            ColorDepth::TrueColor
+        } else if term.contains("256color") {
+            ColorDepth::Ansi256
+        } else if term == "dumb" {
+            ColorDepth::Monochrome
+        } else {
+            ColorDepth::Ansi16
+        }
+    }
+
+    // the closest colour the terminal can show
+    pub fn map(self, color: Color) -> Color {
+        match (self, color) {
+            (ColorDepth::TrueColor, color) | (_, color @ Color::Reset) => color,
+            (ColorDepth::Monochrome, _) => Color::Reset,
+            (ColorDepth::Ansi256, Color::Rgb(r, g, b)) => Color::Indexed(nearest_256((r, g, b))),
+            (ColorDepth::Ansi256, color) => color,
+            (ColorDepth::Ansi16, Color::Rgb(r, g, b)) => nearest_16((r, g, b)),
+            (ColorDepth::Ansi16, Color::Indexed(i)) => nearest_16(indexed_rgb(i)),
+            (ColorDepth::Ansi16, color) => color,
+        }
+    }
+
+    // maps every cell drawn, once per frame
+    pub fn adapt(self, buf: &mut Buffer) {
+        if self == ColorDepth::TrueColor {
+            return;
+        }
+        for cell in buf.content.iter_mut() {
+            cell.fg = self.map(cell.fg);
+            cell.bg = self.map(cell.bg);
+        }
+    }
+}
+
+fn distance(a: (u8, u8, u8), b: (u8, u8, u8)) -> u32 {
+    let d = |x: u8, y: u8| (x as i32 - y as i32).pow(2) as u32;
+    d(a.0, b.0) + d(a.1, b.1) + d(a.2, b.2)
+}
+
+// the closest entry of the cube or of the grey ramp, the first 16 colours vary between terminals
+fn nearest_256(rgb: (u8, u8, u8)) -> u8 {
+    let level = |c: u8| {
+        (0..6)
+            .min_by_key(|&i| (CUBE_LEVELS[i] as i32 - c as i32).abs())
+            .unwrap_or_default() as u8
+    };
+    let (r, g, b) = (level(rgb.0), level(rgb.1), level(rgb.2));
+    let cube = 16 + 36 * r + 6 * g + b;
+    let average = (rgb.0 as u32 + rgb.1 as u32 + rgb.2 as u32) / 3;
+    let grey = 232 + (average.saturating_sub(3) / 10).min(23) as u8;
+    if distance(rgb, indexed_rgb(grey)) < distance(rgb, indexed_rgb(cube)) {
+        grey
+    } else {
+        cube
+    }
+}
+
+fn nearest_16(rgb: (u8, u8, u8)) -> Color {
+    ANSI16
+        .iter()
+        .min_by_key(|(_, c)| distance(rgb, *c))
+        .map_or(Color::Reset, |(color, _)| *color)
+}
+
+// how xterm draws an entry of the 256-colour palette
+fn indexed_rgb(i: u8) -> (u8, u8, u8) {
+    match i {
+        0..=15 => ANSI16[i as usize].1,
+        16..=231 => {
+            let i = i - 16;
+            let level = |n: u8| CUBE_LEVELS[n as usize];
+            (level(i / 36), level(i / 6 % 6), level(i % 6))
+        }
+        _ => {
+            let grey = 8 + 10 * (i - 232);
+            (grey, grey, grey)
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use ratatui::layout::Rect;
+
+    use super::*;
+
+    fn detect(vars: &[(&str, &str)]) -> ColorDepth {
+        ColorDepth::from_env(|name| {
+            vars.iter()
+                .find(|(n, _)| *n == name)
+                .map(|(_, v)| v.to_string())
+        })
+    }
+
+    #[test]
+    fn detects_the_depth_from_the_environment() {
+        assert_eq!(
+            detect(&[("COLORTERM", "truecolor"), ("TERM", "xterm-256color")]),
+            ColorDepth::TrueColor
+        );
+        assert_eq!(detect(&[("TERM", "xterm-direct")]), ColorDepth::TrueColor);
+        assert_eq!(detect(&[("TERM", "xterm-256color")]), ColorDepth::Ansi256);
+        assert_eq!(detect(&[("TERM", "xterm")]), ColorDepth::Ansi16);
+        assert_eq!(detect(&[]), ColorDepth::Ansi16);
+        assert_eq!(detect(&[("TERM", "dumb")]), ColorDepth::Monochrome);
+        assert_eq!(
+            detect(&[("NO_COLOR", "1"), ("COLORTERM", "truecolor")]),
+            ColorDepth::Monochrome
+        );
+        assert_eq!(
+            detect(&[("NO_COLOR", ""), ("TERM", "xterm")]),
+            ColorDepth::Ansi16
+        );
+    }
+
+    #[test]
+    fn maps_to_the_closest_of_256_colours() {
+        let map = |r, g, b| ColorDepth::Ansi256.map(Color::Rgb(r, g, b));
+        // exact entries of the cube and of the grey ramp
+        assert_eq!(map(0, 0, 0), Color::Indexed(16));
+        assert_eq!(map(255, 255, 255), Color::Indexed(231));
+        assert_eq!(map(95, 135, 175), Color::Indexed(16 + 36 + 12 + 3));
+        assert_eq!(map(128, 128, 128), Color::Indexed(244));
+        assert_eq!(map(224, 224, 224), Color::Indexed(254));
+        // the classic 1 and 2 tiles
+        assert_eq!(map(102, 204, 255), Color::Indexed(81));
+        assert_eq!(map(255, 102, 128), Color::Indexed(204));
+        assert_eq!(ColorDepth::Ansi256.map(Color::Red), Color::Red);
+    }
+
+    #[test]
+    fn maps_to_the_closest_of_16_colours() {
+        let map = |color| ColorDepth::Ansi16.map(color);
+        assert_eq!(map(Color::Rgb(102, 204, 255)), Color::LightBlue);
+        assert_eq!(map(Color::Rgb(255, 0, 43)), Color::LightRed);
+        assert_eq!(map(Color::Rgb(28, 28, 28)), Color::Black);
+        assert_eq!(map(Color::Rgb(224, 224, 224)), Color::Gray);
+        assert_eq!(map(Color::Indexed(231)), Color::White);
+        assert_eq!(map(Color::Indexed(9)), Color::LightRed);
+        assert_eq!(map(Color::Blue), Color::Blue);
+    }
+
+    #[test]
+    fn every_index_maps_back_to_itself() {
+        for i in 16..=255 {
+            let (r, g, b) = indexed_rgb(i);
+            assert_eq!(nearest_256((r, g, b)), i, "{:?}", (r, g, b));
+        }
+    }
+
+    #[test]
+    fn monochrome_drops_every_colour() {
+        let mut buf = Buffer::empty(Rect::new(0, 0, 2, 1));
+        buf.get_mut(0, 0)
+            .set_fg(Color::Rgb(1, 2, 3))
+            .set_bg(Color::Red);
+        ColorDepth::Monochrome.adapt(&mut buf);
+        assert_eq!(buf.get(0, 0).fg, Color::Reset);
+        assert_eq!(buf.get(0, 0).bg, Color::Reset);
+        assert_eq!(ColorDepth::Ansi16.map(Color::Reset), Color::Reset);
+        assert_eq!(
+            ColorDepth::TrueColor.map(Color::Rgb(1, 2, 3)),
+            Color::Rgb(1, 2, 3)
+        );
+    }
+}
@@ -0,0 +1,68 @@
+use ratatui::{
+    buffer::Buffer,
+    layout::{Constraint, Rect},
+    style::{Modifier, Style, Stylize},
+    widgets::{Block, Row, Table, Widget},
+};
+use threes::scores::HighScores;
+
+// the high score table, optionally highlighting one of its entries
+pub struct Scoreboard<'a> {
+    scores: &'a HighScores,
+    highlight: Option<usize>,
+    block: Option<Block<'a>>,
+}
+
+impl<'a> Scoreboard<'a> {
+    pub fn new(scores: &'a HighScores) -> Scoreboard<'a> {
+        Scoreboard {
+            scores,
+            highlight: None,
+            block: None,
+        }
+    }
+
+    pub fn highlight(mut self, rank: Option<usize>) -> Scoreboard<'a> {
+        self.highlight = rank;
+        self
+    }
+
+    pub fn block(mut self, block: Block<'a>) -> Scoreboard<'a> {
+        self.block = Some(block);
+        self
+    }
+}
+
+impl<'a> Widget for Scoreboard<'a> {
+    fn render(self, area: Rect, buf: &mut Buffer) {
+        let header = Row::new(vec!["#", "score", "tile", "moves", "date", "seed"]).dark_gray();
+        let rows = self.scores.entries().iter().enumerate().map(|(i, e)| {
+            let row = Row::new(vec![
+                (i + 1).to_string(),
+                e.score.to_string(),
+                e.highest_tile.to_string(),
+                e.moves.to_string(),
+                e.date(),
+                e.seed.to_string(),
+            ]);
+            if self.highlight == Some(i) {
+                row.style(Style::new().add_modifier(Modifier::REVERSED))
+            } else {
+                row
+            }
+        });
+        let widths = [
+            Constraint::Length(2),
+            Constraint::Length(8),
+            Constraint::Length(5),
+            Constraint::Length(6),
+            Constraint::Length(10),
+            Constraint::Min(0),
+        ];
+        let mut table = Table::new(rows, widths).header(header);
+        if let Some(block) = self.block {
+            table = table.block(block);
+        }
+        Widget::render(table, area, buf);
+    }
+}
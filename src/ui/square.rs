@@ -1,20 +1,59 @@
-use std::fmt::Display;
-
-use num::{Num, NumCast};
 use ratatui::{
     buffer::Buffer,
     layout::{Margin, Rect},
     style::Style,
     text::Line,
-    widgets::{Block, Widget},
+    widgets::{Block, BorderType, Borders, Widget},
+};
+
+use super::{
+    layouts::TILE_WIDTH,
+    theme::{Colors, Markers, Theme},
 };
 
-use super::theme::{Theme, EMPTY_THEME, ONE_THEME, TWO_THEME};
+// between the square's edges and its coloured inside
+const MARGIN: Margin = Margin {
+    horizontal: 2,
+    vertical: 1,
+};
+// a pulsing square grows into its margin
+const PULSE_MARGIN: Margin = Margin {
+    horizontal: 1,
+    vertical: 0,
+};
+
+// tells a tile's class apart without its colours
+#[derive(Debug, PartialEq, Eq, Copy, Clone)]
+enum Marker {
+    Glyph(&'static str),
+    Border(BorderType),
+}
+
+impl Marker {
+    // tiles of 1, of 2 and higher ones each have their own
+    fn of(markers: Markers, elem: u32) -> Option<Marker> {
+        let class = match elem {
+            0 => return None,
+            1 => 0,
+            2 => 1,
+            _ => 2,
+        };
+        match markers {
+            Markers::None => None,
+            Markers::Glyphs => Some(Marker::Glyph(["●", "■", "▲"][class])),
+            Markers::Borders => Some(Marker::Border(
+                [BorderType::Rounded, BorderType::Double, BorderType::Thick][class],
+            )),
+        }
+    }
+}
 
 #[derive(Debug, Clone)]
 pub struct Square<'a> {
     label: Line<'a>,
-    theme: Theme,
+    colors: Colors,
+    marker: Option<Marker>,
+    margin: Margin,
     block: Option<Block<'a>>,
 }
 
@@ -22,31 +61,58 @@ impl<'a> Square<'a> {
     pub fn new<T: Into<Line<'a>>>(label: T) -> Square<'a> {
         Square {
             label: label.into(),
-            theme: EMPTY_THEME,
+            colors: Colors::default(),
+            marker: None,
+            margin: MARGIN,
             block: None,
         }
     }
 
-    pub fn theme(mut self, theme: Theme) -> Square<'a> {
-        self.theme = theme;
+    pub fn colors(mut self, colors: Colors) -> Square<'a> {
+        self.colors = colors;
         self
     }
 
-    pub fn from_elem<T: Num + NumCast + Display + Copy>(elem: T) -> Square<'a> {
-        match elem {
-            zero if zero == T::zero() => Square::new("").theme(EMPTY_THEME),
-            one if one == T::one() => Square::new("1").theme(ONE_THEME),
-            two if two == T::one() + T::one() => Square::new("2").theme(TWO_THEME),
-            other => Square::new(elem.to_string()).theme(Theme::new(other)),
+    // a tile, or an empty cell for 0
+    pub fn from_elem(elem: u32, theme: &Theme) -> Square<'a> {
+        let label = if elem == 0 {
+            String::new()
+        } else {
+            elem.to_string()
+        };
+        let mut square = Square::new(label).colors(theme.tile(elem));
+        square.marker = Marker::of(theme.markers, elem);
+        square
+    }
+
+    // a tile yet to be placed, bonus tiles show their candidates or + if they don't fit
+    pub fn preview(values: &[u32], theme: &Theme) -> Square<'a> {
+        if values.len() <= 1 {
+            return Square::from_elem(values.first().copied().unwrap_or_default(), theme);
+        }
+        let label = values
+            .iter()
+            .map(u32::to_string)
+            .collect::<Vec<_>>()
+            .join("/");
+        if label.len() as u16 > TILE_WIDTH - 2 * MARGIN.horizontal {
+            Square::new("+").colors(theme.panel)
+        } else {
+            Square::new(label).colors(theme.panel)
         }
     }
 
+    pub fn pulse(mut self) -> Square<'a> {
+        self.margin = PULSE_MARGIN;
+        self
+    }
+
     pub fn block(mut self, block: Block<'a>) -> Square<'a> {
         self.block = Some(block);
         self
     }
 
-    fn render_block(&mut self, area: &mut Rect, buf: &mut Buffer) -> () {
+    fn render_block(&mut self, area: &mut Rect, buf: &mut Buffer) {
         if let Some(block) = self.block.take() {
             let inner_area = block.inner(*area);
             block.render(*area, buf);
@@ -56,18 +122,31 @@ impl<'a> Square<'a> {
 }
 
 impl<'a> Widget for Square<'a> {
-    fn render(
-        mut self,
-        mut area: ratatui::prelude::Rect,
-        buf: &mut ratatui::prelude::Buffer,
-    ) -> () {
-        let Theme {
+    fn render(mut self, mut area: ratatui::prelude::Rect, buf: &mut ratatui::prelude::Buffer) {
+        let Colors {
             background,
             text,
             shadow,
-        } = self.theme;
-        let inner_rect = area.inner(&Margin::new(2, 1));
-        buf.set_style(inner_rect, Style::new().bg(background).fg(text));
+            modifier,
+        } = self.colors;
+        let inner_rect = area.inner(&self.margin);
+        let style = Style::new().bg(background).add_modifier(modifier);
+
+        // in the margin around the coloured inside, a block's own borders cover its top and bottom
+        if let Some(Marker::Border(border_type)) = self.marker {
+            let frame = Rect {
+                x: inner_rect.x.saturating_sub(1),
+                y: inner_rect.y.saturating_sub(1),
+                width: inner_rect.width + 2,
+                height: inner_rect.height + 2,
+            };
+            Block::new()
+                .borders(Borders::ALL)
+                .border_type(border_type)
+                .border_style(Style::new().fg(background))
+                .render(frame.intersection(area).intersection(buf.area), buf);
+        }
+        buf.set_style(inner_rect, style.fg(text));
 
         self.render_block(&mut area, buf);
 
@@ -76,10 +155,17 @@ impl<'a> Widget for Square<'a> {
                 inner_rect.x,
                 inner_rect.y + inner_rect.height - 1,
                 "▁".repeat(inner_rect.width as usize),
-                Style::new().bg(background).fg(shadow),
+                style.fg(shadow),
             );
         }
 
+        // in the top left corner, clear of the label
+        if let Some(Marker::Glyph(glyph)) = self.marker {
+            if inner_rect.height > 2 && inner_rect.width > 1 {
+                buf.set_string(inner_rect.x + 1, inner_rect.y, glyph, style.fg(text));
+            }
+        }
+
         buf.set_line(
             inner_rect.x + (inner_rect.width.saturating_sub(self.label.width() as u16)) / 2,
             inner_rect.y + (inner_rect.height.saturating_sub(1)) / 2,
@@ -88,3 +174,72 @@ impl<'a> Widget for Square<'a> {
         );
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::ui::theme::Themes;
+
+    #[test]
+    fn preview_shows_the_tile_if_known() {
+        let themes = Themes::load(None).unwrap();
+        assert_eq!(
+            Square::preview(&[2], themes.current()).label,
+            Line::from("2")
+        );
+    }
+
+    fn render(square: Square) -> Buffer {
+        let area = Rect::new(0, 0, TILE_WIDTH, 7);
+        let mut buf = Buffer::empty(area);
+        square.render(area, &mut buf);
+        buf
+    }
+
+    #[test]
+    fn glyphs_mark_every_class_of_tile() {
+        let theme = Themes::load(None)
+            .unwrap()
+            .with_markers(Markers::Glyphs)
+            .current()
+            .clone();
+        let glyph = |elem| {
+            render(Square::from_elem(elem, &theme))
+                .get(3, 1)
+                .symbol()
+                .to_string()
+        };
+        assert_eq!(glyph(1), "●");
+        assert_eq!(glyph(2), "■");
+        assert_eq!(glyph(3), "▲");
+        assert_eq!(glyph(96), "▲");
+        assert_eq!(glyph(0), " ");
+    }
+
+    #[test]
+    fn borders_frame_every_class_of_tile_differently() {
+        let corner = |themes: &Themes, elem| {
+            let buf = render(Square::from_elem(elem, themes.current()));
+            buf.get(1, 0).symbol().to_string()
+        };
+        let themes = Themes::load(None).unwrap();
+        assert_eq!(corner(&themes, 1), " ");
+        let themes = themes.with_markers(Markers::Borders);
+        assert_eq!(corner(&themes, 1), "╭");
+        assert_eq!(corner(&themes, 2), "╔");
+        assert_eq!(corner(&themes, 6), "┏");
+        assert_eq!(corner(&themes, 0), " ");
+    }
+
+    #[test]
+    fn preview_lists_bonus_candidates() {
+        let theme = Themes::load(None).unwrap().current().clone();
+        let preview = Square::preview(&[6, 12, 24], &theme);
+        assert_eq!(preview.label, Line::from("6/12/24"));
+        assert_eq!(preview.colors, theme.panel);
+        assert_eq!(
+            Square::preview(&[192, 384, 768], &theme).label,
+            Line::from("+")
+        );
+    }
+}
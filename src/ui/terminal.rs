@@ -0,0 +1,49 @@
+use std::{
+    io::{stdout, Stdout},
+    panic,
+};
+
+use anyhow::{Context, Result};
+use crossterm::{
+    terminal::{disable_raw_mode, enable_raw_mode, EnterAlternateScreen, LeaveAlternateScreen},
+    ExecutableCommand,
+};
+use ratatui::{backend::CrosstermBackend, Terminal};
+
+// panics restore the terminal too, so that their message is readable
+pub fn init_terminal() -> Result<Terminal<CrosstermBackend<Stdout>>> {
+    let hook = panic::take_hook();
+    panic::set_hook(Box::new(move |info| {
+        let _ = restore_terminal();
+        hook(info);
+    }));
+    enable_raw_mode().context("failed to enable raw mode")?;
+    let terminal = stdout()
+        .execute(EnterAlternateScreen)
+        .context("failed to enter alternate mode")
+        .and_then(|_| {
+            Terminal::new(CrosstermBackend::new(stdout())).context("terminal creation failed")
+        });
+    if terminal.is_err() {
+        let _ = restore_terminal();
+    }
+    terminal
+}
+
+// runs the ui, the terminal being restored whether it fails or not
+pub fn with_terminal<T>(
+    run: impl FnOnce(&mut Terminal<CrosstermBackend<Stdout>>) -> Result<T>,
+) -> Result<T> {
+    let mut terminal = init_terminal()?;
+    let res = run(&mut terminal);
+    restore_terminal()?;
+    res
+}
+
+pub fn restore_terminal() -> Result<()> {
+    disable_raw_mode().context("failed to disable raw mode")?;
+    stdout()
+        .execute(LeaveAlternateScreen)
+        .context("failed to leave alternate screen")?;
+    Ok(())
+}
@@ -1,57 +1,321 @@
-use num::{Num, NumCast};
-use ratatui::style::Color;
+use std::{
+    collections::BTreeMap,
+    fs, io,
+    path::{Path, PathBuf},
+};
 
-#[derive(Debug, Clone)]
-pub struct Theme {
+use anyhow::{anyhow, bail, Context, Result};
+use clap::ValueEnum;
+use ratatui::buffer::Buffer;
+use ratatui::style::{Color, Modifier, Style};
+use serde::Deserialize;
+
+use super::palette::ColorDepth;
+
+// the colours of a square, the terminal's own ones by default
+#[derive(Debug, PartialEq, Eq, Copy, Clone)]
+pub struct Colors {
     pub text: Color,
     pub background: Color,
     pub shadow: Color,
+    // only set without colours
+    pub modifier: Modifier,
+}
+
+impl Default for Colors {
+    fn default() -> Colors {
+        Colors {
+            text: Color::Reset,
+            background: Color::Reset,
+            shadow: Color::Reset,
+            modifier: Modifier::empty(),
+        }
+    }
+}
+
+// what tells tiles of 1, of 2 and higher ones apart besides their colours
+#[derive(Debug, Default, PartialEq, Eq, Copy, Clone, ValueEnum, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Markers {
+    #[default]
+    None,
+    // a symbol in the corner of the tile
+    Glyphs,
+    // a frame in one of several line styles around the tile
+    Borders,
+}
+
+// the colours of the board, its tiles and the panels around it
+#[derive(Debug, Clone)]
+pub struct Theme {
+    pub name: String,
+    // by ascending tile value, starting with empty cells
+    tiles: Vec<(u32, Colors)>,
+    // the score and bonus tile previews
+    pub panel: Colors,
+    // behind the tiles, the terminal's own background if none
+    pub board: Option<Color>,
+    pub border: Option<Color>,
+    pub markers: Markers,
+}
+
+const BUNDLED: [(&str, &str); 6] = [
+    ("classic", include_str!("../../themes/classic.toml")),
+    ("dark", include_str!("../../themes/dark.toml")),
+    (
+        "high-contrast",
+        include_str!("../../themes/high-contrast.toml"),
+    ),
+    (
+        "deuteranopia",
+        include_str!("../../themes/deuteranopia.toml"),
+    ),
+    ("protanopia", include_str!("../../themes/protanopia.toml")),
+    ("tritanopia", include_str!("../../themes/tritanopia.toml")),
+];
+
+// a theme file as written
+#[derive(Debug, Deserialize)]
+#[serde(deny_unknown_fields)]
+struct File {
+    // the text colour of squares which don't set theirs
+    text: String,
+    #[serde(default)]
+    markers: Markers,
+    #[serde(default)]
+    board: BoardFile,
+    panel: ColorsFile,
+    tiles: BTreeMap<String, ColorsFile>,
+}
+
+#[derive(Debug, Default, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+struct BoardFile {
+    background: Option<String>,
+    border: Option<String>,
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(deny_unknown_fields)]
+struct ColorsFile {
+    text: Option<String>,
+    background: String,
+    shadow: String,
+}
+
+// colour names, 256-colour indexes or #rrggbb
+fn parse_color(key: &str, s: &str) -> Result<Color> {
+    s.parse().map_err(|_| {
+        anyhow!(
+            "invalid value for `{}`: expected a colour name, an index or #rrggbb, found `{}`",
+            key,
+            s
+        )
+    })
+}
+
+impl ColorsFile {
+    fn parse(&self, key: &str, text: Color) -> Result<Colors> {
+        Ok(Colors {
+            text: match &self.text {
+                Some(s) => parse_color(&format!("{}.text", key), s)?,
+                None => text,
+            },
+            background: parse_color(&format!("{}.background", key), &self.background)?,
+            shadow: parse_color(&format!("{}.shadow", key), &self.shadow)?,
+            modifier: Modifier::empty(),
+        })
+    }
 }
 
 impl Theme {
-    pub fn new<T: Num + NumCast>(elem: T) -> Theme {
-        let (factor, color): (f64, u8) = if let Some(cast) = num::cast::<T, f64>(elem) {
-            // this is not normalizing, everything above will be the same colour
-            let max: f64 = 100.0;
-            let scale: f64 = cast.min(max) / max;
-            let factor: f64 = (1.0 - scale).max(0.3);
-            let color: u8 = (255.0 * factor) as u8;
-            (factor, color)
-        } else {
-            (1.0, 255)
+    pub fn parse(name: &str, s: &str) -> Result<Theme> {
+        let file: File = toml::from_str(s)?;
+        let text = parse_color("text", &file.text)?;
+        let mut tiles = file
+            .tiles
+            .iter()
+            .map(|(value, colors)| {
+                let key = format!("tiles.{}", value);
+                let value = value
+                    .parse::<u32>()
+                    .map_err(|_| anyhow!("invalid key `{}`: expected a tile value", key))?;
+                Ok((value, colors.parse(&key, text)?))
+            })
+            .collect::<Result<Vec<_>>>()?;
+        tiles.sort_by_key(|(value, _)| *value);
+        if tiles.first().map(|(value, _)| *value) != Some(0) {
+            bail!("missing `tiles.0`: the colours of empty cells are needed");
+        }
+        let board = &file.board;
+        Ok(Theme {
+            name: name.to_string(),
+            tiles,
+            panel: file.panel.parse("panel", text)?,
+            board: board
+                .background
+                .as_deref()
+                .map(|s| parse_color("board.background", s))
+                .transpose()?,
+            border: board
+                .border
+                .as_deref()
+                .map(|s| parse_color("board.border", s))
+                .transpose()?,
+            markers: file.markers,
+        })
+    }
+
+    // the colours of the highest value listed which isn't above this one
+    pub fn tile(&self, value: u32) -> Colors {
+        self.tiles
+            .iter()
+            .rev()
+            .find(|(v, _)| *v <= value)
+            .map_or(self.tiles[0].1, |(_, colors)| *colors)
+    }
+
+    // tiles told apart by bold, reverse and underline rather than by colour
+    fn monochrome(&self) -> Theme {
+        let colors = |modifier| Colors {
+            modifier,
+            ..Colors::default()
         };
         Theme {
-            text: Color::Black,
-            background: Color::Rgb(color, color, color),
-            shadow: Color::Rgb(
-                (255.0 * factor) as u8,
-                (204.0 * factor) as u8,
-                (102.0 * factor) as u8,
-            ),
+            name: self.name.clone(),
+            tiles: vec![
+                (0, colors(Modifier::empty())),
+                (1, colors(Modifier::UNDERLINED)),
+                (2, colors(Modifier::BOLD | Modifier::UNDERLINED)),
+                (3, colors(Modifier::REVERSED)),
+                (48, colors(Modifier::BOLD | Modifier::REVERSED)),
+            ],
+            panel: colors(Modifier::REVERSED),
+            board: None,
+            border: None,
+            markers: self.markers,
+        }
+    }
+
+    pub fn border_style(&self) -> Style {
+        match self.border {
+            Some(color) => Style::new().fg(color),
+            None => Style::new(),
         }
     }
 }
 
-pub const EMPTY_THEME: Theme = Theme {
-    text: Color::Black,
-    background: Color::Rgb(109, 130, 124),
-    shadow: Color::Rgb(81, 119, 119),
-};
-pub const ONE_THEME: Theme = Theme {
-    text: Color::Black,
-    background: Color::Rgb(102, 204, 255),
-    shadow: Color::Rgb(0, 67, 255),
-};
-pub const TWO_THEME: Theme = Theme {
-    text: Color::Black,
-    background: Color::Rgb(255, 102, 128),
-    shadow: Color::Rgb(255, 0, 43),
-};
-pub const OTHER_THEME: Theme = Theme {
-    text: Color::Black,
-    background: Color::Rgb(255, 255, 255),
-    shadow: Color::Rgb(255, 204, 102),
-};
+// the bundled themes and the user's, one of which is in use, drawn in as many colours as the
+// terminal has
+#[derive(Debug, Clone)]
+pub struct Themes {
+    themes: Vec<Theme>,
+    current: usize,
+    depth: ColorDepth,
+}
+
+impl Themes {
+    // $XDG_CONFIG_HOME/threes/themes on linux
+    pub fn default_dir() -> Option<PathBuf> {
+        dirs::config_dir().map(|d| d.join("threes").join("themes"))
+    }
+
+    // every `<name>.toml` in the directory, replacing the bundled theme of the same name
+    pub fn load(dir: Option<&Path>) -> Result<Themes> {
+        let mut themes = BUNDLED
+            .iter()
+            .map(|(name, s)| {
+                Theme::parse(name, s).with_context(|| format!("invalid theme {}", name))
+            })
+            .collect::<Result<Vec<_>>>()?;
+        let Some(dir) = dir else {
+            return Ok(Themes {
+                themes,
+                current: 0,
+                depth: ColorDepth::TrueColor,
+            });
+        };
+        let entries = match fs::read_dir(dir) {
+            Ok(entries) => entries,
+            Err(e) if e.kind() == io::ErrorKind::NotFound => {
+                return Ok(Themes {
+                    themes,
+                    current: 0,
+                    depth: ColorDepth::TrueColor,
+                })
+            }
+            Err(e) => return Err(e).with_context(|| format!("failed to read {}", dir.display())),
+        };
+        let mut paths = entries
+            .map(|entry| entry.map(|e| e.path()))
+            .collect::<io::Result<Vec<_>>>()?;
+        paths.retain(|path| path.extension().is_some_and(|ext| ext == "toml"));
+        paths.sort();
+        for path in paths {
+            let name = path.file_stem().unwrap_or_default().to_string_lossy();
+            let s = fs::read_to_string(&path)
+                .with_context(|| format!("failed to read {}", path.display()))?;
+            let theme = Theme::parse(&name, &s)
+                .with_context(|| format!("invalid theme {}", path.display()))?;
+            match themes.iter_mut().find(|t| t.name == theme.name) {
+                Some(bundled) => *bundled = theme,
+                None => themes.push(theme),
+            }
+        }
+        Ok(Themes {
+            themes,
+            current: 0,
+            depth: ColorDepth::TrueColor,
+        })
+    }
+
+    pub fn with_depth(mut self, depth: ColorDepth) -> Themes {
+        if depth == ColorDepth::Monochrome {
+            self.themes = self.themes.iter().map(Theme::monochrome).collect();
+        }
+        self.depth = depth;
+        self
+    }
+
+    // every theme marks its tiles this way
+    pub fn with_markers(mut self, markers: Markers) -> Themes {
+        for theme in &mut self.themes {
+            theme.markers = markers;
+        }
+        self
+    }
+
+    // the colours of a drawn frame mapped to those the terminal has
+    pub fn adapt(&self, buf: &mut Buffer) {
+        self.depth.adapt(buf);
+    }
+
+    pub fn select(mut self, name: &str) -> Result<Themes> {
+        match self.themes.iter().position(|t| t.name == name) {
+            Some(i) => self.current = i,
+            None => bail!(
+                "unknown theme `{}`, expected one of {}",
+                name,
+                self.names().join(", ")
+            ),
+        }
+        Ok(self)
+    }
+
+    pub fn names(&self) -> Vec<&str> {
+        self.themes.iter().map(|t| t.name.as_str()).collect()
+    }
+
+    pub fn current(&self) -> &Theme {
+        &self.themes[self.current]
+    }
+
+    // the next theme, back to the first after the last
+    pub fn cycle(&mut self) -> &Theme {
+        self.current = (self.current + 1) % self.themes.len();
+        self.current()
+    }
+}
 
 #[cfg(test)]
 mod tests {
@@ -59,9 +323,140 @@ mod tests {
 
     use super::*;
 
+    fn classic() -> Theme {
+        Themes::load(None).unwrap().current().clone()
+    }
+
+    #[test]
+    fn bundled_themes_are_valid() {
+        let themes = Themes::load(None).unwrap();
+        assert_eq!(
+            themes.names(),
+            [
+                "classic",
+                "dark",
+                "high-contrast",
+                "deuteranopia",
+                "protanopia",
+                "tritanopia"
+            ]
+        );
+        // colour-blind themes mark their tiles by default
+        let themes = themes.select("tritanopia").unwrap();
+        assert_eq!(themes.current().markers, Markers::Glyphs);
+        assert_ne!(themes.current().tile(1), themes.current().tile(2));
+    }
+
+    #[test]
+    fn tiles_take_the_colours_of_the_closest_value_below() {
+        let theme = classic();
+        assert_eq!(theme.tile(12).background, Color::Rgb(224, 224, 224));
+        assert_eq!(theme.tile(12).text, Color::Black);
+        assert_eq!(theme.tile(13), theme.tile(12));
+        assert_eq!(theme.tile(768), theme.tile(96));
+        assert_eq!(theme.tile(1).background, Color::Rgb(102, 204, 255));
+        assert_eq!(theme.board, None);
+    }
+
+    #[test]
+    fn tiles_can_set_their_own_text() {
+        let theme = Theme::parse(
+            "test",
+            r##"
+            text = "white"
+            [panel]
+            background = "black"
+            shadow = "gray"
+            [tiles]
+            0 = { background = "black", shadow = "gray" }
+            3 = { background = "#102030", shadow = "42", text = "black" }
+            "##,
+        )
+        .unwrap();
+        assert_eq!(theme.tile(0).text, Color::White);
+        assert_eq!(theme.tile(2), theme.tile(0));
+        assert_eq!(
+            theme.tile(6),
+            Colors {
+                text: Color::Black,
+                background: Color::Rgb(16, 32, 48),
+                shadow: Color::Indexed(42),
+                modifier: Modifier::empty(),
+            }
+        );
+    }
+
+    #[test]
+    fn invalid_themes_name_their_key() {
+        let error = |s: &str| format!("{:#}", Theme::parse("test", s).unwrap_err());
+        let theme = |tiles: &str| {
+            format!(
+                "text = \"black\"\n[panel]\nbackground = \"white\"\nshadow = \"white\"\n[tiles]\n{}",
+                tiles
+            )
+        };
+        assert!(
+            error(&theme("0 = { background = \"mauve\", shadow = \"white\" }"))
+                .starts_with("invalid value for `tiles.0.background`")
+        );
+        assert!(
+            error(&theme("x = { background = \"red\", shadow = \"white\" }"))
+                .starts_with("invalid key `tiles.x`")
+        );
+        assert!(
+            error(&theme("3 = { background = \"red\", shadow = \"white\" }"))
+                .starts_with("missing `tiles.0`")
+        );
+        assert!(error(&theme("0 = { background = \"red\" }")).contains("missing field `shadow`"));
+    }
+
+    #[test]
+    fn monochrome_themes_use_modifiers_only() {
+        let themes = Themes::load(None)
+            .unwrap()
+            .with_depth(ColorDepth::Monochrome);
+        let theme = themes.current();
+        assert_eq!(theme.name, "classic");
+        assert_eq!(theme.tile(3).background, Color::Reset);
+        assert_eq!(theme.tile(12).modifier, Modifier::REVERSED);
+        assert_ne!(theme.tile(1).modifier, theme.tile(2).modifier);
+        assert_ne!(theme.tile(24).modifier, theme.tile(48).modifier);
+        assert_eq!(theme.tile(0), Colors::default());
+    }
+
+    #[test]
+    fn markers_can_be_set_for_every_theme() {
+        let themes = Themes::load(None).unwrap();
+        assert_eq!(themes.current().markers, Markers::None);
+        let themes = themes.with_markers(Markers::Borders);
+        assert!(themes.themes.iter().all(|t| t.markers == Markers::Borders));
+    }
+
+    #[test]
+    fn cycles_through_every_theme() {
+        let mut themes = Themes::load(None).unwrap().select("dark").unwrap();
+        assert_eq!(themes.current().name, "dark");
+        assert_eq!(themes.cycle().name, "high-contrast");
+        for _ in 2..BUNDLED.len() {
+            themes.cycle();
+        }
+        assert_eq!(themes.current().name, "classic");
+        assert!(Themes::load(None).unwrap().select("neon").is_err());
+    }
+
     #[test]
-    fn theme_is_modified_by_input_value() -> () {
-        let theme = Theme::new::<u32>(12);
-        assert_eq!(theme.background, Color::Rgb(224, 224, 224));
+    fn user_themes_replace_bundled_ones() {
+        let dir = std::env::temp_dir().join(format!("threes-test-themes-{}", std::process::id()));
+        fs::create_dir_all(&dir).unwrap();
+        let dark = BUNDLED[1].1.replace("#1c1c1c", "#000000");
+        fs::write(dir.join("dark.toml"), dark).unwrap();
+        fs::write(dir.join("mine.toml"), BUNDLED[0].1).unwrap();
+        fs::write(dir.join("notes.txt"), "not a theme").unwrap();
+        let themes = Themes::load(Some(&dir)).unwrap();
+        fs::remove_dir_all(&dir).unwrap();
+        assert_eq!(themes.names().len(), BUNDLED.len() + 1);
+        assert_eq!(themes.names().last(), Some(&"mine"));
+        let dark = themes.select("dark").unwrap();
+        assert_eq!(dark.current().board, Some(Color::Rgb(0, 0, 0)));
     }
 }
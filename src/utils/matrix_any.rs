@@ -1,4 +1,4 @@
-use nalgebra::{SMatrix, Scalar};
+use nalgebra::{DMatrix, SMatrix, Scalar};
 
 pub trait MatrixAny {
     type Item;
@@ -35,6 +35,27 @@ impl<T: Clone + Scalar, const C: usize, const R: usize> MatrixAny for SMatrix<T,
     }
 }
 
+impl<T: Clone + Scalar> MatrixAny for DMatrix<T> {
+    type Item = T;
+
+    fn any_col<F>(&self, mut f: F) -> bool
+    where
+        Self: Sized,
+        F: FnMut(&[Self::Item]) -> bool,
+    {
+        self.column_iter().any(|c| f(c.as_slice()))
+    }
+
+    fn any_row<F>(&self, mut f: F) -> bool
+    where
+        Self: Sized,
+        F: FnMut(&[Self::Item]) -> bool,
+    {
+        // row views are not contiguous, hence the clone_owned
+        self.row_iter().any(|r| f(r.clone_owned().as_slice()))
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use nalgebra::Matrix4;
@@ -68,4 +89,12 @@ mod tests {
         assert!(!m.any_row(|c| c.contains(&0)));
         assert!(m.any_row(|c| c.contains(&1)));
     }
+
+    #[test]
+    fn any_row_and_col_see_whole_lines_of_dynamic_matrices() {
+        let mut m = DMatrix::repeat(2, 3, 1);
+        m[(1, 2)] = 0;
+        assert!(m.any_row(|r| r == [1, 1, 0]));
+        assert!(m.any_col(|c| c == [1, 0]));
+    }
 }
@@ -0,0 +1,205 @@
+use std::{
+    ops::ControlFlow,
+    path::Path,
+    time::{Duration, Instant},
+};
+
+use anyhow::Result;
+use crossterm::event::{self, Event, KeyCode};
+use ratatui::{
+    backend::Backend,
+    layout::{Alignment, Direction},
+    style::Stylize,
+    text::Line,
+    widgets::{Block, Borders, Paragraph},
+    Frame, Terminal,
+};
+use threes::{
+    replay::{self, Replay},
+    state::State,
+};
+
+use crate::{
+    config::{Action, Keys},
+    ui::{
+        board::Board,
+        layouts::{game_layout, HORIZONTAL_SEP, MAIN_LAYOUT},
+        square::Square,
+        terminal::with_terminal,
+        theme::Themes,
+    },
+};
+
+const AUTOPLAY_DELAY: Duration = Duration::from_millis(500);
+
+// jump to the first and the last move unless an action is bound to them
+const JUMP_KEYS: [(KeyCode, &str); 2] = [(KeyCode::Home, "home"), (KeyCode::End, "end")];
+
+// steps forward and backward through a recorded game
+pub struct Viewer {
+    replay: Replay,
+    states: Vec<State>,
+    current: usize,
+    autoplay: bool,
+    last_step: Instant,
+    keys: Keys,
+    themes: Themes,
+}
+
+impl Viewer {
+    fn new(replay: Replay, keys: Keys, themes: Themes) -> Result<Viewer> {
+        let states = replay.states()?;
+        Ok(Viewer {
+            replay,
+            states,
+            current: 0,
+            autoplay: false,
+            last_step: Instant::now(),
+            keys,
+            themes,
+        })
+    }
+
+    pub fn run(path: &Path, keys: Keys, themes: Themes) -> Result<()> {
+        // fail before touching the terminal
+        let mut viewer = Viewer::new(replay::read(path)?, keys, themes)?;
+        with_terminal(|terminal| viewer.event_loop(terminal))
+    }
+
+    fn event_loop(&mut self, terminal: &mut Terminal<impl Backend>) -> Result<()> {
+        loop {
+            let _ = terminal.draw(|frame| {
+                self.ui(frame);
+                self.themes.adapt(frame.buffer_mut());
+            });
+            if self.autoplay && self.last_step.elapsed() >= AUTOPLAY_DELAY {
+                self.step(1);
+            }
+            if !event::poll(Duration::from_millis(100))? {
+                continue;
+            }
+            if let Event::Key(key) = event::read()? {
+                if key.kind != event::KeyEventKind::Press {
+                    continue;
+                }
+                if self.handle_key_event(key).is_break() {
+                    return Ok(());
+                }
+            }
+        }
+    }
+
+    fn step(&mut self, delta: isize) {
+        let last = self.states.len() - 1;
+        self.current = self.current.saturating_add_signed(delta).min(last);
+        self.last_step = Instant::now();
+        if self.current == last {
+            self.autoplay = false;
+        }
+    }
+
+    fn ui(&mut self, frame: &mut Frame) {
+        let state = &self.states[self.current];
+        let theme = self.themes.current();
+        let main_layout = MAIN_LAYOUT.split(frame.size());
+
+        let position = match self.current.checked_sub(1) {
+            Some(i) => format!(
+                "move {}/{}: {}",
+                self.current,
+                self.states.len() - 1,
+                self.replay.steps()[i].direction
+            ),
+            None => format!("move 0/{}", self.states.len() - 1),
+        };
+        frame.render_widget(
+            Paragraph::new(vec![
+                Line::from(
+                    format!(
+                        "{} replay - seed {}",
+                        self.replay.rules(),
+                        self.replay.seed()
+                    )
+                    .dark_gray(),
+                )
+                .alignment(Alignment::Left),
+                Line::from(position.dark_gray()).alignment(Alignment::Left),
+            ]),
+            main_layout[0],
+        );
+
+        // next tile
+        let next_tile_block = Block::new()
+            .borders(Borders::ALL)
+            .border_style(theme.border_style())
+            .title("next tile".dark_gray());
+        let next_tile_widget =
+            Square::preview(&state.tile().preview(), theme).block(next_tile_block);
+        frame.render_widget(next_tile_widget, HORIZONTAL_SEP.split(main_layout[1])[0]);
+
+        // score
+        let score_block = Block::new()
+            .borders(Borders::ALL)
+            .border_style(theme.border_style())
+            .title("score".dark_gray());
+        let score_widget = Square::new(state.score().to_string())
+            .colors(theme.panel)
+            .block(score_block);
+        frame.render_widget(score_widget, HORIZONTAL_SEP.split(main_layout[1])[1]);
+
+        // game
+        let game_block = Block::new()
+            .borders(Borders::ALL)
+            .border_style(theme.border_style())
+            .title(
+                format!(
+                    "use {} {} to step, {}{} to autoplay, {} theme, {} to quit",
+                    self.keys.label(Action::Left),
+                    self.keys.label(Action::Right),
+                    self.jump_help(),
+                    self.keys.label(Action::Autoplay),
+                    self.keys.label(Action::Theme),
+                    self.keys.label(Action::Quit)
+                )
+                .dark_gray(),
+            );
+        let size = state.size();
+        let game_area = game_layout(size.cols, Direction::Horizontal)
+            .split(game_layout(size.rows, Direction::Vertical).split(main_layout[2])[0])[0];
+        frame.render_widget(game_block, game_area);
+        frame.render_widget(Board::new(state.grid(), theme), main_layout[2]);
+    }
+
+    // the jump keys no action took over
+    fn jump_help(&self) -> String {
+        let names: Vec<&str> = JUMP_KEYS
+            .iter()
+            .filter(|(code, _)| self.keys.action(*code).is_none())
+            .map(|(_, name)| *name)
+            .collect();
+        if names.is_empty() {
+            String::new()
+        } else {
+            format!("{} to jump, ", names.join(" "))
+        }
+    }
+
+    fn handle_key_event(&mut self, key: event::KeyEvent) -> ControlFlow<()> {
+        match (self.keys.action(key.code), key.code) {
+            (Some(Action::Left), _) => self.step(-1),
+            (Some(Action::Right), _) => self.step(1),
+            (Some(Action::Autoplay), _) => {
+                self.autoplay = !self.autoplay;
+                self.last_step = Instant::now();
+            }
+            (Some(Action::Quit), _) => return ControlFlow::Break(()),
+            (Some(Action::Theme), _) => {
+                self.themes.cycle();
+            }
+            (None, KeyCode::Home) => self.step(isize::MIN),
+            (None, KeyCode::End) => self.step(isize::MAX),
+            _ => (),
+        }
+        ControlFlow::Continue(())
+    }
+}